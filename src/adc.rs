@@ -10,8 +10,6 @@
 //! to show which pins are available on certain device variants but currently the library doesn't enforce this.
 //! To fully support the right pins would require 10+ more features for the various variants.
 //! ## Todo
-//! * Injected conversions
-//! * Analog watchdog config
 //! * Discontinuous mode
 //! # Examples
 //! ## One-shot conversion
@@ -120,6 +118,48 @@
 //!     tim.bdtr.modify(|_, w| w.moe().set_bit());
 //! }
 //! ```
+//!
+//! ## Injected conversions
+//!
+//! The injected group has its own sequence of up to 4 channels, its own trigger and its
+//! own data registers, so it can interrupt the regular sequence at a precise moment
+//! (e.g. in the middle of a PWM period) without disturbing it.
+//! ```
+//! use stm32f4xx_hal::{
+//!   gpio::gpioa,
+//!   adc::{
+//!     Adc, Event, Flag,
+//!     config::{AdcConfig, SampleTime, InjectedSequence, InjectedExternalTrigger, TriggerMode},
+//!   },
+//! };
+//!
+//! let config = AdcConfig::default()
+//!     .injected_external_trigger(TriggerMode::RisingEdge, InjectedExternalTrigger::Tim_1_cc_4);
+//! let mut adc = Adc::adc1(device.ADC1, true, config);
+//! let pa0 = gpioa.pa0.into_analog();
+//! let pa1 = gpioa.pa1.into_analog();
+//! adc.configure_injected_channel(&pa0, InjectedSequence::One, SampleTime::Cycles_15);
+//! adc.configure_injected_channel(&pa1, InjectedSequence::Two, SampleTime::Cycles_15);
+//! adc.listen(Event::InjectedEndOfConversion);
+//! adc.enable();
+//!
+//! // In the ADC interrupt handler
+//! let ia = adc.injected_sample(InjectedSequence::One);
+//! let ib = adc.injected_sample(InjectedSequence::Two);
+//! adc.clear_flags(Flag::InjectedEndOfConversion);
+//! ```
+//!
+//! ## Analog watchdog
+//!
+//! The analog watchdog raises a flag (and optionally an interrupt) as soon as a conversion
+//! falls outside of the configured thresholds.
+//! ```
+//! use stm32f4xx_hal::adc::{config::WatchdogGroup, Event};
+//!
+//! adc.set_analog_watchdog_thresholds(100, 3000);
+//! adc.enable_analog_watchdog_single(&pa0, WatchdogGroup::RegularAndInjected);
+//! adc.listen(Event::AnalogWatchdog);
+//! ```
 
 #![deny(missing_docs)]
 
@@ -138,9 +178,12 @@ use crate::{
     signature::VDDA_CALIB,
 };
 use core::fmt;
+use enumflags2::BitFlags;
 
 pub mod config;
 mod f4;
+#[cfg(feature = "adc2")]
+pub mod multi;
 
 /// Vref internal signal, used for calibration
 pub struct Vref;
@@ -151,6 +194,40 @@ pub struct Vbat;
 /// Core temperature internal signal
 pub struct Temperature;
 
+/// ADC interrupt events
+#[enumflags2::bitflags]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[repr(u32)]
+pub enum Event {
+    /// Analog watchdog interrupt enable
+    AnalogWatchdog = 1 << 6,
+    /// Injected end of conversion interrupt enable
+    InjectedEndOfConversion = 1 << 7,
+    /// Overrun interrupt enable
+    Overrun = 1 << 26,
+}
+
+/// ADC status flags
+#[enumflags2::bitflags]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[repr(u32)]
+pub enum Flag {
+    /// Analog watchdog
+    AnalogWatchdog = 1 << 0,
+    /// Regular channel end of conversion
+    EndOfConversion = 1 << 1,
+    /// Injected channel end of conversion
+    InjectedEndOfConversion = 1 << 2,
+    /// Injected channel start
+    InjectedStart = 1 << 3,
+    /// Regular channel start
+    RegularStart = 1 << 4,
+    /// Overrun
+    Overrun = 1 << 5,
+}
+
 /// Marker trait for all ADC peripherals
 pub trait Instance: rcc::Instance + crate::Ptr<RB = pac::adc1::RegisterBlock> {}
 
@@ -320,6 +397,7 @@ impl<ADC: Instance> Adc<ADC> {
         self.set_align(config.align);
         self.set_scan(config.scan);
         self.set_external_trigger(config.external_trigger);
+        self.set_injected_external_trigger(config.injected_external_trigger);
         self.set_continuous(config.continuous);
         self.set_dma(config.dma);
         self.set_end_of_conversion_interrupt(config.end_of_conversion_interrupt);
@@ -534,6 +612,10 @@ impl<ADC: Instance> Adc<ADC> {
         };
 
         //Set the sample time for the channel
+        self.set_channel_sample_time(channel, sample_time);
+    }
+
+    fn set_channel_sample_time(&mut self, channel: u8, sample_time: config::SampleTime) {
         let st = sample_time as u8;
         match channel {
             0..=9 => self
@@ -627,6 +709,230 @@ impl<ADC: Instance> Adc<ADC> {
     }
 }
 
+impl<ADC: Instance> Adc<ADC> {
+    /// Sets which external trigger to use for the injected group and if it is disabled, rising, falling or both
+    pub fn set_injected_external_trigger(
+        &mut self,
+        (edge, jextsel): (config::TriggerMode, config::InjectedExternalTrigger),
+    ) {
+        self.config.injected_external_trigger = (edge, jextsel);
+        self.adc_reg.cr2().modify(|_, w| {
+            unsafe {
+                w.jextsel().bits(jextsel as _);
+            }
+            w.jexten().set(edge as _)
+        });
+    }
+
+    /// Returns the current injected sequence length
+    pub fn injected_sequence_length(&mut self) -> u8 {
+        self.adc_reg.jsqr().read().jl().bits() + 1
+    }
+
+    /// Reset the injected sequence
+    pub fn reset_injected_sequence(&mut self) {
+        //The reset state is One conversion selected
+        self.adc_reg
+            .jsqr()
+            .modify(|_, w| w.jl().set(config::InjectedSequence::One.into()));
+    }
+
+    /// Configure a channel for injected sampling.
+    /// It will make sure the injected sequence is at least as long as the `sequence` provided.
+    /// # Arguments
+    /// * `channel` - channel to configure
+    /// * `sequence` - where in the injected sequence to sample the channel. Also called rank in some STM docs/code
+    /// * `sample_time` - how long to sample for. Note that the sample time is shared with the regular
+    ///   sequence if the channel is configured there too
+    pub fn configure_injected_channel<CHANNEL>(
+        &mut self,
+        _channel: &CHANNEL,
+        sequence: config::InjectedSequence,
+        sample_time: config::SampleTime,
+    ) where
+        CHANNEL: embedded_hal_02::adc::Channel<ADC, ID = u8>,
+    {
+        let channel = CHANNEL::channel();
+
+        // The injected sequencer converts JSQ(4 - length)..=JSQ4, so the already configured ranks
+        // have to be moved when the sequence grows.
+        self.adc_reg.jsqr().modify(|r, w| {
+            let prev_len = r.jl().bits() + 1;
+            let len = prev_len.max(u8::from(sequence) + 1);
+            let mut channels = [0u8; 4];
+            for (rank, ch) in channels.iter_mut().enumerate().take(prev_len as usize) {
+                *ch = r.jsq(4 - prev_len + rank as u8).bits();
+            }
+            channels[sequence as usize] = channel;
+            for (rank, ch) in channels.iter().enumerate().take(len as usize) {
+                unsafe {
+                    w.jsq(4 - len + rank as u8).bits(*ch);
+                }
+            }
+            w.jl().set(len - 1)
+        });
+
+        //Set the sample time for the channel
+        self.set_channel_sample_time(channel, sample_time);
+    }
+
+    /// Sets the offset subtracted from the raw conversion of an injected rank.
+    /// The result in the injected data register is signed when the offset is not zero.
+    pub fn set_injected_offset(&mut self, sequence: config::InjectedSequence, offset: u16) {
+        self.adc_reg
+            .jofr(sequence as usize)
+            .write(|w| unsafe { w.joffset().bits(offset) });
+    }
+
+    /// Enables and disables the automatic conversion of the injected group after the regular group
+    pub fn set_injected_auto(&mut self, auto: bool) {
+        self.adc_reg.cr1().modify(|_, w| w.jauto().bit(auto));
+    }
+
+    /// Starts the injected conversion sequence. Waits for the hardware to indicate it's actually started.
+    pub fn start_injected_conversion(&mut self) {
+        self.enable();
+        self.clear_injected_end_of_conversion_flag();
+        //Start conversion
+        self.adc_reg.cr2().modify(|_, w| w.jswstart().set_bit());
+
+        while !self.adc_reg.sr().read().jstrt().bit_is_set() {}
+    }
+
+    /// Resets the injected end-of-conversion flag
+    pub fn clear_injected_end_of_conversion_flag(&mut self) {
+        self.adc_reg
+            .sr()
+            .write(|w| unsafe { w.bits(0x3f).jeoc().clear_bit() });
+    }
+
+    /// Block until the injected conversion sequence is completed
+    /// # Panics
+    /// Will panic if there is no injected conversion started and the injected end-of-conversion bit is not set
+    pub fn wait_for_injected_conversion_sequence(&self) {
+        if !self.adc_reg.sr().read().jstrt().bit_is_set()
+            && !self.adc_reg.sr().read().jeoc().bit_is_set()
+        {
+            panic!("Waiting for injected end-of-conversion but no conversion started");
+        }
+        while !self.adc_reg.sr().read().jeoc().bit_is_set() {}
+        //Clear the injected conversion started flag
+        self.adc_reg
+            .sr()
+            .write(|w| unsafe { w.bits(0x3f).jstrt().clear_bit() });
+    }
+
+    /// Returns the sample of an injected rank stored in the corresponding injected data register.
+    /// If an offset is configured for the rank the value should be interpreted as `i16`.
+    pub fn injected_sample(&self, sequence: config::InjectedSequence) -> u16 {
+        self.adc_reg.jdr(sequence as usize).read().jdata().bits()
+    }
+
+    /// Returns the address of the injected data register of `sequence`.
+    pub fn injected_data_register_address(&self, sequence: config::InjectedSequence) -> u32 {
+        self.adc_reg.jdr(sequence as usize).as_ptr() as u32
+    }
+}
+
+impl<ADC: Instance> Adc<ADC> {
+    /// Sets the low and high thresholds of the analog watchdog.
+    /// The thresholds are compared to the 12-bit right aligned conversion result,
+    /// independently of the configured resolution and alignment.
+    pub fn set_analog_watchdog_thresholds(&mut self, low: u16, high: u16) {
+        self.adc_reg
+            .ltr()
+            .write(|w| unsafe { w.lt().bits(low & 0xfff) });
+        self.adc_reg
+            .htr()
+            .write(|w| unsafe { w.ht().bits(high & 0xfff) });
+    }
+
+    /// Enables the analog watchdog on all channels of the selected `group`
+    pub fn enable_analog_watchdog_all(&mut self, group: config::WatchdogGroup) {
+        let (regular, injected) = group.enables();
+        self.adc_reg.cr1().modify(|_, w| {
+            w.awdsgl().clear_bit();
+            w.awden().bit(regular);
+            w.jawden().bit(injected)
+        });
+    }
+
+    /// Enables the analog watchdog on a single `channel` of the selected `group`
+    pub fn enable_analog_watchdog_single<CHANNEL>(
+        &mut self,
+        _channel: &CHANNEL,
+        group: config::WatchdogGroup,
+    ) where
+        CHANNEL: embedded_hal_02::adc::Channel<ADC, ID = u8>,
+    {
+        let (regular, injected) = group.enables();
+        self.adc_reg.cr1().modify(|_, w| {
+            unsafe {
+                w.awdch().bits(CHANNEL::channel());
+            }
+            w.awdsgl().set_bit();
+            w.awden().bit(regular);
+            w.jawden().bit(injected)
+        });
+    }
+
+    /// Disables the analog watchdog on both regular and injected groups
+    pub fn disable_analog_watchdog(&mut self) {
+        self.adc_reg.cr1().modify(|_, w| {
+            w.awden().clear_bit();
+            w.jawden().clear_bit()
+        });
+    }
+
+    /// Returns if the analog watchdog detected a conversion outside of the thresholds
+    pub fn is_analog_watchdog_triggered(&self) -> bool {
+        self.adc_reg.sr().read().awd().bit_is_set()
+    }
+}
+
+impl<ADC: Instance> crate::Listen for Adc<ADC> {
+    type Event = Event;
+
+    fn listen_event(
+        &mut self,
+        disable: Option<BitFlags<Self::Event>>,
+        enable: Option<BitFlags<Self::Event>>,
+    ) {
+        self.adc_reg.cr1().modify(|r, w| unsafe {
+            w.bits({
+                let mut bits = r.bits();
+                if let Some(d) = disable {
+                    bits &= !d.bits();
+                }
+                if let Some(e) = enable {
+                    bits |= e.bits();
+                }
+                bits
+            })
+        });
+    }
+}
+
+impl<ADC: Instance> crate::ClearFlags for Adc<ADC> {
+    type Flag = Flag;
+
+    #[inline(always)]
+    fn clear_flags(&mut self, flags: impl Into<BitFlags<Self::Flag>>) {
+        self.adc_reg
+            .sr()
+            .write(|w| unsafe { w.bits(0x3f & !flags.into().bits()) });
+    }
+}
+
+impl<ADC: Instance> crate::ReadFlags for Adc<ADC> {
+    type Flag = Flag;
+
+    #[inline(always)]
+    fn flags(&self) -> BitFlags<Self::Flag> {
+        BitFlags::from_bits_truncate(self.adc_reg.sr().read().bits())
+    }
+}
+
 impl<ADC: Instance> Adc<ADC> {
     fn read<PIN>(&mut self, pin: &mut PIN) -> nb::Result<u16, ()>
     where
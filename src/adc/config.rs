@@ -69,6 +69,27 @@ impl From<u8> for Sequence {
     }
 }
 
+/// The place in the injected sequence a given channel should be captured
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[repr(u8)]
+pub enum InjectedSequence {
+    /// 1
+    One = 0,
+    /// 2
+    Two = 1,
+    /// 3
+    Three = 2,
+    /// 4
+    Four = 3,
+}
+
+impl From<InjectedSequence> for u8 {
+    fn from(s: InjectedSequence) -> u8 {
+        s as _
+    }
+}
+
 /// The number of cycles to sample a given channel for
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -196,6 +217,50 @@ impl From<ExternalTrigger> for u8 {
     }
 }
 
+/// Possible external triggers for the injected group
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum InjectedExternalTrigger {
+    /// TIM1 compare channel 4
+    Tim_1_cc_4 = 0b0000,
+    /// TIM1 trigger out
+    Tim_1_trgo = 0b0001,
+    /// TIM2 compare channel 1
+    Tim_2_cc_1 = 0b0010,
+    /// TIM2 trigger out
+    Tim_2_trgo = 0b0011,
+    /// TIM3 compare channel 2
+    Tim_3_cc_2 = 0b0100,
+    /// TIM3 compare channel 4
+    Tim_3_cc_4 = 0b0101,
+    /// TIM4 compare channel 1
+    Tim_4_cc_1 = 0b0110,
+    /// TIM4 compare channel 2
+    Tim_4_cc_2 = 0b0111,
+    /// TIM4 compare channel 3
+    Tim_4_cc_3 = 0b1000,
+    /// TIM4 trigger out
+    Tim_4_trgo = 0b1001,
+    /// TIM5 compare channel 4
+    Tim_5_cc_4 = 0b1010,
+    /// TIM5 trigger out
+    Tim_5_trgo = 0b1011,
+    /// TIM8 compare channel 2
+    Tim_8_cc_2 = 0b1100,
+    /// TIM8 compare channel 3
+    Tim_8_cc_3 = 0b1101,
+    /// TIM8 compare channel 4
+    Tim_8_cc_4 = 0b1110,
+    /// External interrupt line 15
+    Exti_15 = 0b1111,
+}
+impl From<InjectedExternalTrigger> for u8 {
+    fn from(et: InjectedExternalTrigger) -> u8 {
+        et as _
+    }
+}
+
 /// Possible trigger modes
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -216,6 +281,27 @@ impl From<TriggerMode> for u8 {
     }
 }
 
+/// Conversion groups guarded by the analog watchdog
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum WatchdogGroup {
+    /// Guard regular conversions only
+    Regular,
+    /// Guard injected conversions only
+    Injected,
+    /// Guard both regular and injected conversions
+    RegularAndInjected,
+}
+impl WatchdogGroup {
+    pub(crate) fn enables(self) -> (bool, bool) {
+        match self {
+            WatchdogGroup::Regular => (true, false),
+            WatchdogGroup::Injected => (false, true),
+            WatchdogGroup::RegularAndInjected => (true, true),
+        }
+    }
+}
+
 /// Data register alignment
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
 #[derive(Copy, Clone, PartialEq, Eq, Debug)]
@@ -305,6 +391,7 @@ pub struct AdcConfig {
     pub(crate) align: Align,
     pub(crate) scan: Scan,
     pub(crate) external_trigger: (TriggerMode, ExternalTrigger),
+    pub(crate) injected_external_trigger: (TriggerMode, InjectedExternalTrigger),
     pub(crate) continuous: Continuous,
     pub(crate) dma: Dma,
     pub(crate) end_of_conversion_interrupt: Eoc,
@@ -338,6 +425,15 @@ impl AdcConfig {
         self.external_trigger = (trigger_mode, trigger);
         self
     }
+    /// change the injected_external_trigger field
+    pub fn injected_external_trigger(
+        mut self,
+        trigger_mode: TriggerMode,
+        trigger: InjectedExternalTrigger,
+    ) -> Self {
+        self.injected_external_trigger = (trigger_mode, trigger);
+        self
+    }
     /// change the continuous field
     pub fn continuous(mut self, continuous: Continuous) -> Self {
         self.continuous = continuous;
@@ -377,6 +473,7 @@ impl Default for AdcConfig {
             align: Align::Right,
             scan: Scan::Disabled,
             external_trigger: (TriggerMode::Disabled, ExternalTrigger::Tim_1_cc_1),
+            injected_external_trigger: (TriggerMode::Disabled, InjectedExternalTrigger::Tim_1_cc_4),
             continuous: Continuous::Single,
             dma: Dma::Disabled,
             end_of_conversion_interrupt: Eoc::Disabled,
@@ -0,0 +1,295 @@
+//! Dual and triple ADC modes
+//!
+//! In multi ADC mode ADC1 is the master and ADC2 (and ADC3) are slaves which are converted
+//! together with, or interleaved with, the master. The conversion results of all ADCs are
+//! available in the common data register, so a single DMA stream is enough to move the samples.
+//!
+//! # Example
+//! ```
+//! use stm32f4xx_hal::adc::{
+//!     config::{AdcConfig, Dma, SampleTime, Sequence},
+//!     multi::{DualMode, MultiAdc},
+//!     Adc,
+//! };
+//!
+//! let mut adc1 = Adc::new(dp.ADC1, true, AdcConfig::default(), &mut rcc);
+//! let mut adc2 = Adc::new(dp.ADC2, false, AdcConfig::default(), &mut rcc);
+//! adc1.configure_channel(&current, Sequence::One, SampleTime::Cycles_3);
+//! adc2.configure_channel(&voltage, Sequence::One, SampleTime::Cycles_3);
+//!
+//! let mut adcs = MultiAdc::dual(adc1, adc2, DualMode::RegularSimultaneous);
+//! adcs.set_dma(Dma::Continuous);
+//! // `adcs` can now be used as the peripheral of a DMA2 stream 0 or 4 transfer,
+//! // each `u32` word holds ADC2 in the upper and ADC1 in the lower half-word.
+//! adcs.start_conversion();
+//! ```
+
+use super::{config, Adc};
+use crate::dma::traits::{DMASet, PeriAddress, SafePeripheralRead};
+use crate::dma::PeripheralToMemory;
+#[cfg(feature = "adc3")]
+use crate::pac::ADC3;
+use crate::pac::{self, ADC1, ADC2};
+use core::marker::PhantomData;
+
+/// Dual ADC mode (ADC1 and ADC2)
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum DualMode {
+    /// Combined regular simultaneous and injected simultaneous mode
+    RegularInjectedSimultaneous = 0b00001,
+    /// Combined regular simultaneous and alternate trigger mode
+    RegularSimultaneousAlternateTrigger = 0b00010,
+    /// Injected simultaneous mode only
+    InjectedSimultaneous = 0b00101,
+    /// Regular simultaneous mode only
+    RegularSimultaneous = 0b00110,
+    /// Interleaved mode only
+    Interleaved = 0b00111,
+    /// Alternate trigger mode only
+    AlternateTrigger = 0b01001,
+}
+
+/// Triple ADC mode (ADC1, ADC2 and ADC3)
+#[cfg(feature = "adc3")]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[repr(u8)]
+pub enum TripleMode {
+    /// Combined regular simultaneous and injected simultaneous mode
+    RegularInjectedSimultaneous = 0b10001,
+    /// Combined regular simultaneous and alternate trigger mode
+    RegularSimultaneousAlternateTrigger = 0b10010,
+    /// Injected simultaneous mode only
+    InjectedSimultaneous = 0b10101,
+    /// Regular simultaneous mode only
+    RegularSimultaneous = 0b10110,
+    /// Interleaved mode only
+    Interleaved = 0b10111,
+    /// Alternate trigger mode only
+    AlternateTrigger = 0b11001,
+}
+
+/// Word size of the DMA transfers from the common data register
+///
+/// * `u16` selects DMA mode 1: one half-word per conversion, in the order ADC1, ADC2(, ADC3).
+///   Used in triple regular simultaneous mode.
+/// * `u32` selects DMA mode 2: two half-words per request, e.g. ADC2 and ADC1 in dual mode.
+///   Used in dual regular simultaneous and in interleaved modes.
+pub trait DmaWord: crate::Sealed {
+    #[doc(hidden)]
+    const DMA_MODE: u8;
+}
+
+impl DmaWord for u16 {
+    const DMA_MODE: u8 = 0b01;
+}
+
+impl DmaWord for u32 {
+    const DMA_MODE: u8 = 0b10;
+}
+
+/// ADCs working together in dual or triple mode
+///
+/// `ADCS` is the tuple of owned ADCs, `W` is the size of the words read from the
+/// common data register by DMA.
+pub struct MultiAdc<ADCS, W: DmaWord = u32> {
+    adcs: ADCS,
+    dma: config::Dma,
+    _word: PhantomData<W>,
+}
+
+fn common() -> &'static pac::adc_common::RegisterBlock {
+    unsafe { &*pac::ADC_COMMON::ptr() }
+}
+
+impl MultiAdc<(Adc<ADC1>, Adc<ADC2>)> {
+    /// Combines ADC1 (master) and ADC2 (slave) in dual mode.
+    ///
+    /// The external trigger of the slave is disabled, conversions of both ADCs are
+    /// started by the trigger (or software start) of the master.
+    pub fn dual(adc1: Adc<ADC1>, mut adc2: Adc<ADC2>, mode: DualMode) -> Self {
+        adc2.set_external_trigger((
+            config::TriggerMode::Disabled,
+            config::ExternalTrigger::Tim_1_cc_1,
+        ));
+        adc2.set_injected_external_trigger((
+            config::TriggerMode::Disabled,
+            config::InjectedExternalTrigger::Tim_1_cc_4,
+        ));
+        common()
+            .ccr()
+            .modify(|_, w| unsafe { w.multi().bits(mode as u8) });
+        Self {
+            adcs: (adc1, adc2),
+            dma: config::Dma::Disabled,
+            _word: PhantomData,
+        }
+    }
+}
+
+impl<W: DmaWord> MultiAdc<(Adc<ADC1>, Adc<ADC2>), W> {
+    /// Enables all ADCs
+    pub fn enable(&mut self) {
+        self.adcs.1.enable();
+        self.adcs.0.enable();
+    }
+
+    /// Starts the regular conversion of all ADCs
+    pub fn start_conversion(&mut self) {
+        self.adcs.1.enable();
+        self.adcs.0.start_conversion();
+    }
+
+    /// Returns to independent mode and releases the ADCs
+    pub fn release(self) -> (Adc<ADC1>, Adc<ADC2>) {
+        Self::reset_common();
+        self.adcs
+    }
+}
+
+#[cfg(feature = "adc3")]
+impl MultiAdc<(Adc<ADC1>, Adc<ADC2>, Adc<ADC3>)> {
+    /// Combines ADC1 (master), ADC2 and ADC3 (slaves) in triple mode.
+    ///
+    /// The external triggers of the slaves are disabled, conversions of all ADCs are
+    /// started by the trigger (or software start) of the master.
+    pub fn triple(
+        adc1: Adc<ADC1>,
+        mut adc2: Adc<ADC2>,
+        mut adc3: Adc<ADC3>,
+        mode: TripleMode,
+    ) -> Self {
+        adc2.set_external_trigger((
+            config::TriggerMode::Disabled,
+            config::ExternalTrigger::Tim_1_cc_1,
+        ));
+        adc2.set_injected_external_trigger((
+            config::TriggerMode::Disabled,
+            config::InjectedExternalTrigger::Tim_1_cc_4,
+        ));
+        adc3.set_external_trigger((
+            config::TriggerMode::Disabled,
+            config::ExternalTrigger::Tim_1_cc_1,
+        ));
+        adc3.set_injected_external_trigger((
+            config::TriggerMode::Disabled,
+            config::InjectedExternalTrigger::Tim_1_cc_4,
+        ));
+        common()
+            .ccr()
+            .modify(|_, w| unsafe { w.multi().bits(mode as u8) });
+        Self {
+            adcs: (adc1, adc2, adc3),
+            dma: config::Dma::Disabled,
+            _word: PhantomData,
+        }
+    }
+}
+
+#[cfg(feature = "adc3")]
+impl<W: DmaWord> MultiAdc<(Adc<ADC1>, Adc<ADC2>, Adc<ADC3>), W> {
+    /// Enables all ADCs
+    pub fn enable(&mut self) {
+        self.adcs.2.enable();
+        self.adcs.1.enable();
+        self.adcs.0.enable();
+    }
+
+    /// Starts the regular conversion of all ADCs
+    pub fn start_conversion(&mut self) {
+        self.adcs.2.enable();
+        self.adcs.1.enable();
+        self.adcs.0.start_conversion();
+    }
+
+    /// Returns to independent mode and releases the ADCs
+    pub fn release(self) -> (Adc<ADC1>, Adc<ADC2>, Adc<ADC3>) {
+        Self::reset_common();
+        self.adcs
+    }
+}
+
+impl<ADCS, W: DmaWord> MultiAdc<ADCS, W> {
+    /// Returns the owned ADCs, e.g. to configure their channels
+    pub fn adcs(&mut self) -> &mut ADCS {
+        &mut self.adcs
+    }
+
+    /// Changes the word size of the DMA transfers from the common data register
+    pub fn dma_word<W2: DmaWord>(self) -> MultiAdc<ADCS, W2> {
+        let s = MultiAdc {
+            adcs: self.adcs,
+            dma: self.dma,
+            _word: PhantomData,
+        };
+        s.apply_dma();
+        s
+    }
+
+    /// Sets the delay between 2 sampling phases in interleaved mode, in ADC clock cycles (5..=20)
+    pub fn set_delay(&mut self, cycles: u8) {
+        assert!((5..=20).contains(&cycles));
+        common()
+            .ccr()
+            .modify(|_, w| unsafe { w.delay().bits(cycles - 5) });
+    }
+
+    /// Sets DMA to disabled, single or continuous for the common data register.
+    /// The DMA request of the individual ADCs must stay disabled.
+    pub fn set_dma(&mut self, dma: config::Dma) {
+        self.dma = dma;
+        self.apply_dma();
+    }
+
+    fn apply_dma(&self) {
+        let (dds, mode) = match self.dma {
+            config::Dma::Disabled => (false, 0),
+            config::Dma::Single => (false, W::DMA_MODE),
+            config::Dma::Continuous => (true, W::DMA_MODE),
+        };
+        common().ccr().modify(|_, w| {
+            w.dds().bit(dds);
+            unsafe { w.dma().bits(mode) }
+        });
+    }
+
+    fn reset_common() {
+        common().ccr().modify(|_, w| unsafe {
+            w.multi().bits(0);
+            w.dma().bits(0);
+            w.dds().clear_bit()
+        });
+    }
+
+    /// Returns the current content of the common data register,
+    /// the data of the master ADC is in the lower half-word.
+    pub fn current_sample(&self) -> u32 {
+        common().cdr().read().bits()
+    }
+
+    /// Returns the address of the common data register. Primarily useful for configuring DMA.
+    pub fn data_register_address(&self) -> u32 {
+        common().cdr().as_ptr() as u32
+    }
+}
+
+impl<ADCS, W: DmaWord> SafePeripheralRead for MultiAdc<ADCS, W> {}
+
+unsafe impl<ADCS, W: DmaWord> PeriAddress for MultiAdc<ADCS, W> {
+    #[inline(always)]
+    fn address(&self) -> u32 {
+        self.data_register_address()
+    }
+
+    type MemSize = W;
+}
+
+// The common data register is served by the DMA request of the master ADC
+unsafe impl<ADCS, W: DmaWord, STREAM, const CHANNEL: u8> DMASet<STREAM, CHANNEL, PeripheralToMemory>
+    for MultiAdc<ADCS, W>
+where
+    ADC1: DMASet<STREAM, CHANNEL, PeripheralToMemory>,
+{
+}
@@ -0,0 +1,89 @@
+//! Async support for DMA transfers
+//!
+//! The interrupt handler of every stream used asynchronously must call
+//! [`StreamX::on_interrupt`]:
+//!
+//! ```
+//! #[interrupt]
+//! fn DMA2_STREAM0() {
+//!     StreamX::<DMA2, 0>::on_interrupt();
+//! }
+//! ```
+
+use super::{
+    traits::{Channel, DMASet, Direction, DmaFlagExt, PeriAddress, Stream},
+    ChannelX, DmaEvent, DmaFlag, Instance, StreamX, Transfer, TransferError,
+};
+use crate::Listen;
+use core::{
+    future::poll_fn,
+    task::{Context, Poll},
+};
+use enumflags2::BitFlags;
+
+impl<DMA: Instance, const S: u8> StreamX<DMA, S>
+where
+    Self: Stream,
+{
+    /// Handles the interrupt of the stream for async transfers.
+    ///
+    /// Disables the interrupts of the stream and wakes the task awaiting it,
+    /// which checks the flags and listens again if the transfer is not finished.
+    pub fn on_interrupt() {
+        Self::new().unlisten(BitFlags::ALL);
+        Self::waker().wake();
+    }
+}
+
+/// Checks the transfer complete and error flags of the stream, listens to them if none is set
+pub(crate) fn poll_stream<STREAM: Stream>(
+    stream: &mut STREAM,
+    cx: &mut Context<'_>,
+) -> Poll<Result<(), TransferError>> {
+    STREAM::waker().register(cx.waker());
+    let flags = stream.flags();
+    if flags.is_transfer_error() {
+        stream.clear_flags(DmaFlag::TransferError | DmaFlag::TransferComplete);
+        Poll::Ready(Err(TransferError::Transfer))
+    } else if flags.is_direct_mode_error() {
+        stream.clear_flags(DmaFlag::DirectModeError | DmaFlag::TransferComplete);
+        Poll::Ready(Err(TransferError::DirectMode))
+    } else if flags.is_transfer_complete() {
+        stream.clear_flags(DmaFlag::TransferComplete | DmaFlag::HalfTransfer);
+        Poll::Ready(Ok(()))
+    } else {
+        stream.listen(
+            DmaEvent::TransferComplete | DmaEvent::TransferError | DmaEvent::DirectModeError,
+        );
+        Poll::Pending
+    }
+}
+
+/// Waits until the transfer complete flag or one of the error flags of the stream is set
+pub(crate) async fn wait_stream<STREAM: Stream>(stream: &mut STREAM) -> Result<(), TransferError> {
+    poll_fn(|cx| poll_stream(stream, cx)).await
+}
+
+impl<STREAM, const CHANNEL: u8, PERIPHERAL, DIR, BUF>
+    Transfer<STREAM, CHANNEL, PERIPHERAL, DIR, BUF>
+where
+    STREAM: Stream,
+    ChannelX<CHANNEL>: Channel,
+    DIR: Direction,
+    PERIPHERAL: PeriAddress + DMASet<STREAM, CHANNEL, DIR>,
+{
+    /// Waits for the transfer to complete without blocking.
+    ///
+    /// The transfer complete flag is cleared. In double buffer or circular mode this resolves
+    /// once per completed buffer. Dropping the `Transfer` aborts it.
+    pub async fn wait_async(&mut self) -> Result<(), TransferError> {
+        wait_stream(&mut self.stream).await
+    }
+
+    /// Polls the transfer for completion, registering the waker of `cx` if it is still running.
+    ///
+    /// Useful to wait for the transfer together with other events, e.g. peripheral errors.
+    pub fn poll_complete(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), TransferError>> {
+        poll_stream(&mut self.stream, cx)
+    }
+}
@@ -0,0 +1,267 @@
+//! Memory to memory copies and fills with a DMA2 stream
+//!
+//! Only DMA2 can transfer from memory to memory. The data size, the bursts and the FIFO
+//! are selected from the alignment of the buffers:
+//!
+//! ```
+//! let streams = StreamsTuple::new(dp.DMA2, &mut rcc);
+//! let mut dma = DmaMemcpy::new(streams.0);
+//! dma.copy(&src, &mut dst)?;
+//! dma.fill(0xff00_00ffu32, &mut framebuffer)?;
+//! ```
+//!
+//! The async variants need `StreamX::on_interrupt` in the interrupt handler of the stream.
+
+use core::{
+    mem,
+    sync::atomic::{compiler_fence, Ordering},
+};
+
+use super::{
+    config::{BurstMode, FifoThreshold, Priority},
+    stream_disable,
+    traits::{DmaFlagExt, Stream},
+    DmaChannel, DmaDataSize, DmaDirection, DmaFlag, DmaFlowController, StreamX, TransferError,
+};
+#[cfg(feature = "async")]
+use crate::OnDrop;
+use crate::{pac::DMA2, ClearFlags, ReadFlags};
+
+/// Values written by [`DmaMemcpy::fill`]
+pub trait FillValue: crate::Sealed + Copy {}
+
+impl FillValue for u8 {}
+impl FillValue for u16 {}
+impl FillValue for u32 {}
+
+/// FIFO size in bytes, a burst fills it completely
+const FIFO_SIZE: usize = 16;
+
+/// Memory to memory job split into chunks of at most 65535 data items
+struct Job {
+    src: u32,
+    src_increment: bool,
+    dst: u32,
+    size: DmaDataSize,
+    burst: BurstMode,
+    /// Remaining data items
+    remaining: usize,
+}
+
+impl Job {
+    /// Selects the largest data size `src`, `dst` and `bytes` are aligned to,
+    /// bursts if also aligned to the FIFO size
+    fn new(src: u32, src_increment: bool, dst: u32, bytes: usize, size: Option<usize>) -> Self {
+        let size = size.unwrap_or_else(|| {
+            [4, 2, 1]
+                .into_iter()
+                .find(|&s| {
+                    (src as usize).is_multiple_of(s)
+                        && (dst as usize).is_multiple_of(s)
+                        && bytes.is_multiple_of(s)
+                })
+                .unwrap_or(1)
+        });
+        let count = bytes / size;
+        let beats = FIFO_SIZE / size;
+        let aligned = (dst as usize).is_multiple_of(FIFO_SIZE)
+            && (!src_increment || (src as usize).is_multiple_of(FIFO_SIZE))
+            && count.is_multiple_of(beats);
+        let (size, burst) = match size {
+            4 => (DmaDataSize::Word, BurstMode::Burst4),
+            2 => (DmaDataSize::HalfWord, BurstMode::Burst8),
+            _ => (DmaDataSize::Byte, BurstMode::Burst16),
+        };
+
+        Self {
+            src,
+            src_increment,
+            dst,
+            size,
+            burst: if aligned { burst } else { BurstMode::NoBurst },
+            remaining: count,
+        }
+    }
+
+    fn item_size(&self) -> usize {
+        match self.size {
+            DmaDataSize::Byte => 1,
+            DmaDataSize::HalfWord => 2,
+            DmaDataSize::Word => 4,
+        }
+    }
+
+    /// Configures and enables `stream` for the next chunk, returns `false` when done
+    fn start_next<STREAM: Stream>(&mut self, stream: &mut STREAM) -> bool {
+        if self.remaining == 0 {
+            return false;
+        }
+        // Chunks of whole bursts
+        let max = match self.burst {
+            BurstMode::NoBurst => u16::MAX as usize,
+            _ => {
+                u16::MAX as usize / (FIFO_SIZE / self.item_size()) * (FIFO_SIZE / self.item_size())
+            }
+        };
+        let count = self.remaining.min(max);
+
+        stream_disable(stream);
+        stream.clear_all_flags();
+        stream.set_channel(DmaChannel::Channel0);
+        stream.set_priority(Priority::Low);
+        stream.set_direction(DmaDirection::MemoryToMemory);
+        stream.set_flow_controller(DmaFlowController::Dma);
+        stream.set_circular_mode(false);
+        stream.set_double_buffer(false);
+        // The FIFO is required for memory to memory transfers
+        stream.set_fifo_enable(true);
+        stream.set_fifo_threshold(FifoThreshold::Full);
+        // NOTE(unsafe) the addresses are aligned to the data size
+        unsafe {
+            stream.set_memory_size(self.size);
+            stream.set_peripheral_size(self.size);
+        }
+        stream.set_memory_burst(self.burst);
+        stream.set_peripheral_burst(self.burst);
+        // The peripheral port is the source
+        stream.set_peripheral_increment(self.src_increment);
+        stream.set_memory_increment(true);
+        stream.set_peripheral_address(self.src);
+        stream.set_memory_address(self.dst);
+        stream.set_number_of_transfers(count as u16);
+
+        // "Preceding reads and writes cannot be moved past subsequent writes"
+        compiler_fence(Ordering::Release);
+        unsafe { stream.enable() };
+
+        let bytes = (count * self.item_size()) as u32;
+        self.dst += bytes;
+        if self.src_increment {
+            self.src += bytes;
+        }
+        self.remaining -= count;
+        true
+    }
+}
+
+/// Copies and fills memory with a DMA2 stream
+pub struct DmaMemcpy<const S: u8> {
+    stream: StreamX<DMA2, S>,
+}
+
+impl<const S: u8> DmaMemcpy<S>
+where
+    StreamX<DMA2, S>: Stream,
+{
+    /// Uses `stream` for memory to memory transfers
+    pub fn new(stream: StreamX<DMA2, S>) -> Self {
+        Self { stream }
+    }
+
+    /// Returns the stream
+    pub fn release(mut self) -> StreamX<DMA2, S> {
+        stream_disable(&mut self.stream);
+        self.stream.set_fifo_enable(false);
+        self.stream
+    }
+
+    /// Copies `src` to `dst`, blocking until done.
+    ///
+    /// # Panics
+    ///
+    /// * When `src` and `dst` have different lengths.
+    pub fn copy<T: Copy>(&mut self, src: &[T], dst: &mut [T]) -> Result<(), TransferError> {
+        let mut job = Self::copy_job(src, dst);
+        self.run(&mut job)
+    }
+
+    /// Fills `dst` with `value`, blocking until done.
+    pub fn fill<V: FillValue>(&mut self, value: V, dst: &mut [V]) -> Result<(), TransferError> {
+        let mut job = Self::fill_job(&value, dst);
+        self.run(&mut job)
+    }
+
+    /// Copies `src` to `dst`, waiting for the stream interrupt.
+    ///
+    /// # Panics
+    ///
+    /// * When `src` and `dst` have different lengths.
+    #[cfg(feature = "async")]
+    pub async fn copy_async<T: Copy>(
+        &mut self,
+        src: &[T],
+        dst: &mut [T],
+    ) -> Result<(), TransferError> {
+        let mut job = Self::copy_job(src, dst);
+        self.run_async(&mut job).await
+    }
+
+    /// Fills `dst` with `value`, waiting for the stream interrupt.
+    #[cfg(feature = "async")]
+    pub async fn fill_async<V: FillValue>(
+        &mut self,
+        value: V,
+        dst: &mut [V],
+    ) -> Result<(), TransferError> {
+        // `value` is the source of the whole job, it lives in this future
+        let mut job = Self::fill_job(&value, dst);
+        self.run_async(&mut job).await
+    }
+
+    fn copy_job<T>(src: &[T], dst: &mut [T]) -> Job {
+        assert_eq!(src.len(), dst.len());
+        Job::new(
+            src.as_ptr() as u32,
+            true,
+            dst.as_mut_ptr() as u32,
+            mem::size_of_val(src),
+            None,
+        )
+    }
+
+    fn fill_job<V: FillValue>(value: &V, dst: &mut [V]) -> Job {
+        Job::new(
+            value as *const V as u32,
+            false,
+            dst.as_mut_ptr() as u32,
+            mem::size_of_val(dst),
+            Some(mem::size_of::<V>()),
+        )
+    }
+
+    fn run(&mut self, job: &mut Job) -> Result<(), TransferError> {
+        while job.start_next(&mut self.stream) {
+            let result = loop {
+                let flags = self.stream.flags();
+                if flags.is_transfer_error() {
+                    break Err(TransferError::Transfer);
+                } else if flags.is_direct_mode_error() {
+                    break Err(TransferError::DirectMode);
+                } else if flags.is_transfer_complete() {
+                    break Ok(());
+                }
+            };
+            stream_disable(&mut self.stream);
+            self.stream
+                .clear_flags(DmaFlag::TransferComplete | DmaFlag::HalfTransfer);
+            compiler_fence(Ordering::Acquire);
+            result?;
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "async")]
+    async fn run_async(&mut self, job: &mut Job) -> Result<(), TransferError> {
+        // Disables the stream, i.e. aborts the job if the future is dropped
+        let mut stream = OnDrop::new(&mut self.stream, |stream| {
+            stream_disable(*stream);
+            compiler_fence(Ordering::SeqCst);
+        });
+        while job.start_next(&mut **stream) {
+            super::future::wait_stream(&mut **stream).await?;
+        }
+        drop(stream);
+        compiler_fence(Ordering::Acquire);
+        Ok(())
+    }
+}
@@ -28,6 +28,15 @@ use traits::{
     SafePeripheralRead, Stream, StreamISR,
 };
 
+mod ring_buffer;
+pub use ring_buffer::RingBuffer;
+#[cfg(feature = "async")]
+mod future;
+#[cfg(feature = "async")]
+pub(crate) use future::poll_stream;
+mod memcpy;
+pub use memcpy::{DmaMemcpy, FillValue};
+
 /// Errors.
 #[derive(PartialEq, Eq)]
 pub enum DMAError<T> {
@@ -39,6 +48,16 @@ pub enum DMAError<T> {
     Overrun(T),
 }
 
+/// Errors of a DMA transfer waited for until it completes
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum TransferError {
+    /// Bus error during the transfer (TEIF)
+    Transfer,
+    /// Direct mode error (DMEIF)
+    DirectMode,
+}
+
 // Manually implement `Debug`, so we can have debug information even with a buffer `T` that doesn't
 // implement `Debug`. `T` is always a buffer type chosen by the user, because of that the debug
 // information can be helpful even without knowing the inner type
@@ -664,6 +683,11 @@ where
             CurrentBuffer::FirstBuffer
         }
     }
+    #[cfg(feature = "async")]
+    #[inline(always)]
+    fn waker() -> &'static atomic_waker::AtomicWaker {
+        &I::wakers()[S as usize]
+    }
 }
 
 impl<I: Instance, const S: u8> StreamX<I, S>
@@ -984,7 +1008,7 @@ where
 
 // utility function to disable gracefully the stream. It disable stream, wait until stream is
 // disabled and prevent during process
-fn stream_disable<T: Stream>(stream: &mut T) {
+pub(crate) fn stream_disable<T: Stream>(stream: &mut T) {
     if stream.is_enabled() {
         // Aborting an on-going transfer might cause interrupts to fire, disable
         let interrupts = stream.events();
@@ -0,0 +1,234 @@
+//! Circular mode DMA ring buffer for continuous peripheral to memory transfers
+//!
+//! The stream writes the buffer round and round, the reader follows it using the
+//! number of remaining transfers (NDTR) as the write position:
+//!
+//! ```
+//! let buffer = cortex_m::singleton!(: [u8; 64] = [0; 64]).unwrap();
+//! let mut ring = RingBuffer::new(dma2.2, rx, buffer, DmaConfig::default());
+//! ring.start(|_rx| {});
+//!
+//! let mut bytes = [0; 16];
+//! let count = ring.read(&mut bytes)?;
+//! ```
+
+use core::{
+    ptr,
+    sync::atomic::{compiler_fence, Ordering},
+};
+
+use embedded_dma::WriteBuffer;
+
+use super::{
+    config::DmaConfig,
+    stream_disable,
+    traits::{Channel, DMASet, PeriAddress, Stream, StreamISR},
+    ChannelX, DMAError, PeripheralToMemory, Transfer,
+};
+
+/// Ring buffer continuously filled by a stream in circular mode
+///
+/// Elements must be read before the stream writes the whole buffer again,
+/// otherwise [`read`](Self::read) reports an overrun.
+pub struct RingBuffer<STREAM, const CHANNEL: u8, PERIPHERAL, BUF>
+where
+    STREAM: Stream,
+    PERIPHERAL: PeriAddress,
+{
+    transfer: Transfer<STREAM, CHANNEL, PERIPHERAL, PeripheralToMemory, BUF>,
+    buf_ptr: *const PERIPHERAL::MemSize,
+    len: usize,
+    /// Index of the next element to read
+    read_index: usize,
+    /// The stream wrapped around since the reader did, so it writes behind the read index
+    wrapped: bool,
+}
+
+impl<STREAM, const CHANNEL: u8, PERIPHERAL, BUF> RingBuffer<STREAM, CHANNEL, PERIPHERAL, BUF>
+where
+    STREAM: Stream,
+    ChannelX<CHANNEL>: Channel,
+    PERIPHERAL: PeriAddress + DMASet<STREAM, CHANNEL, PeripheralToMemory>,
+    PERIPHERAL::MemSize: Copy,
+    BUF: WriteBuffer<Word = <PERIPHERAL as PeriAddress>::MemSize>,
+{
+    /// Configures the stream in circular mode to receive from `peripheral` into `buf`.
+    ///
+    /// Memory increment is always enabled and double buffering is disabled in `config`.
+    ///
+    /// # Panics
+    ///
+    /// * When `buf` is empty or longer than 65535 elements.
+    pub fn new(stream: STREAM, peripheral: PERIPHERAL, mut buf: BUF, config: DmaConfig) -> Self {
+        // NOTE(unsafe) We now own this buffer and we won't call any &mut methods on it until the
+        // ring buffer is released
+        let (buf_ptr, len) = unsafe { buf.write_buffer() };
+        assert!(len > 0 && len <= u16::MAX as usize);
+
+        let mut transfer = Transfer::init_peripheral_to_memory(
+            stream,
+            peripheral,
+            buf,
+            None,
+            config.memory_increment(true).double_buffer(false),
+        );
+        transfer.stream.set_circular_mode(true);
+
+        Self {
+            transfer,
+            buf_ptr,
+            len,
+            read_index: 0,
+            wrapped: false,
+        }
+    }
+
+    /// Starts the stream, the closure will be executed right after enabling it.
+    pub fn start<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut PERIPHERAL),
+    {
+        self.transfer.start(f);
+    }
+
+    /// Pauses the stream, the closure will be executed right before disabling it.
+    ///
+    /// The elements received so far can still be read.
+    pub fn pause<F>(&mut self, f: F)
+    where
+        F: FnOnce(&mut PERIPHERAL),
+    {
+        self.transfer.pause(f);
+    }
+
+    /// Stops the stream and returns the underlying resources.
+    pub fn release(mut self) -> (STREAM, PERIPHERAL, BUF) {
+        stream_disable(&mut self.transfer.stream);
+        self.transfer.stream.set_circular_mode(false);
+        let (stream, peripheral, buf, _) = self.transfer.release();
+        (stream, peripheral, buf)
+    }
+
+    /// Capacity of the ring buffer.
+    pub fn capacity(&self) -> usize {
+        self.len
+    }
+
+    /// Index of the element the stream writes next.
+    fn write_index(&self) -> usize {
+        (self.len - self.transfer.number_of_transfers() as usize) % self.len
+    }
+
+    /// Returns the write index, checking that the stream did not overtake the reader.
+    fn update(&mut self) -> Result<usize, DMAError<()>> {
+        let mut write_index = self.write_index();
+        if self.transfer.is_transfer_complete() {
+            // The stream wrapped around, NDTR is read again, as it could have
+            // been read just before the wrap
+            self.transfer.clear_transfer_complete();
+            write_index = self.write_index();
+            if self.wrapped {
+                return Err(DMAError::Overrun(()));
+            }
+            self.wrapped = true;
+        }
+
+        if self.wrapped == (write_index < self.read_index) {
+            Ok(write_index)
+        } else {
+            // Either the stream wrapped around and passed the reader,
+            // or it wrapped around and the flag was cleared by someone else
+            Err(DMAError::Overrun(()))
+        }
+    }
+
+    /// Returns the number of elements available to read.
+    ///
+    /// Returns an overrun error if the stream has overwritten unread elements,
+    /// in that case the reader skips to the current write position.
+    pub fn len(&mut self) -> Result<usize, DMAError<()>> {
+        match self.update() {
+            Ok(write_index) if self.wrapped => Ok(self.len - self.read_index + write_index),
+            Ok(write_index) => Ok(write_index - self.read_index),
+            Err(e) => {
+                self.skip_to_write_index();
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns `true` if there are no elements to read.
+    pub fn is_empty(&mut self) -> Result<bool, DMAError<()>> {
+        self.len().map(|len| len == 0)
+    }
+
+    /// Discards all received elements.
+    pub fn clear(&mut self) {
+        self.skip_to_write_index();
+    }
+
+    fn skip_to_write_index(&mut self) {
+        self.transfer.clear_transfer_complete();
+        self.read_index = self.write_index();
+        self.wrapped = false;
+    }
+
+    /// Copies the newly received elements to `buf`, returns the number of copied elements.
+    ///
+    /// Returns an overrun error if the stream has overwritten unread elements, also while they
+    /// were copied. In that case the reader skips to the current write position, so that the
+    /// next read returns the elements received after the overrun.
+    pub fn read(&mut self, buf: &mut [PERIPHERAL::MemSize]) -> Result<usize, DMAError<()>> {
+        let mut count = 0;
+        while count < buf.len() {
+            let write_index = match self.update() {
+                Ok(write_index) => write_index,
+                Err(e) => {
+                    self.skip_to_write_index();
+                    return Err(e);
+                }
+            };
+            // Copy up to the end of the buffer, the rest after wrapping around in the next loop
+            let end = if self.wrapped { self.len } else { write_index };
+            let n = (end - self.read_index).min(buf.len() - count);
+            if n == 0 {
+                break;
+            }
+
+            // "Subsequent reads and writes cannot be moved ahead of preceding reads"
+            compiler_fence(Ordering::Acquire);
+            // NOTE(unsafe) the elements between the read and the write index are not
+            // written by the stream, which is checked again after the copy
+            unsafe {
+                ptr::copy_nonoverlapping(
+                    self.buf_ptr.add(self.read_index),
+                    buf[count..].as_mut_ptr(),
+                    n,
+                );
+            }
+            compiler_fence(Ordering::Acquire);
+            if let Err(e) = self.update() {
+                self.skip_to_write_index();
+                return Err(e);
+            }
+
+            count += n;
+            self.read_index += n;
+            if self.read_index == self.len {
+                self.read_index = 0;
+                self.wrapped = false;
+            }
+        }
+        Ok(count)
+    }
+}
+
+// NOTE(unsafe) the buffer pointer is only read through the ring buffer, which owns the buffer
+unsafe impl<STREAM, const CHANNEL: u8, PERIPHERAL, BUF> Send
+    for RingBuffer<STREAM, CHANNEL, PERIPHERAL, BUF>
+where
+    STREAM: Stream + Send,
+    PERIPHERAL: PeriAddress + Send,
+    BUF: Send,
+{
+}
@@ -256,6 +256,11 @@ pub trait Stream: StreamISR + crate::Listen<Event = DmaEvent> + crate::Sealed {
 
     /// Get which buffer is currently in use by the DMA.
     fn current_buffer(&self) -> CurrentBuffer;
+
+    /// Waker of the task awaiting this stream.
+    #[cfg(feature = "async")]
+    #[doc(hidden)]
+    fn waker() -> &'static atomic_waker::AtomicWaker;
 }
 
 /// DMA direction.
@@ -301,10 +306,29 @@ macro_rules! address {
 pub type DMARegisterBlock = pac::dma1::RegisterBlock;
 
 /// Trait that represents an instance of a DMA peripheral.
-pub trait Instance: rcc::Instance + crate::Ptr<RB = DMARegisterBlock> {}
+pub trait Instance: rcc::Instance + crate::Ptr<RB = DMARegisterBlock> {
+    /// Wakers of the tasks awaiting the streams of this DMA.
+    #[cfg(feature = "async")]
+    #[doc(hidden)]
+    fn wakers() -> &'static [atomic_waker::AtomicWaker; 8];
+}
+
+macro_rules! dma_instance {
+    ($($DMA:ty),+) => {
+        $(
+            impl Instance for $DMA {
+                #[cfg(feature = "async")]
+                fn wakers() -> &'static [atomic_waker::AtomicWaker; 8] {
+                    static WAKERS: [atomic_waker::AtomicWaker; 8] =
+                        [const { atomic_waker::AtomicWaker::new() }; 8];
+                    &WAKERS
+                }
+            }
+        )+
+    };
+}
 
-impl Instance for DMA1 {}
-impl Instance for DMA2 {}
+dma_instance!(DMA1, DMA2);
 
 /// A trait for marker tha represent Channel of a DMA stream.
 pub trait Channel {
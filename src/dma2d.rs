@@ -0,0 +1,570 @@
+//! Chrom-ART Accelerator (DMA2D)
+//!
+//! The DMA2D fills rectangles of a frame buffer with a color, copies images into them
+//! with pixel format conversion, and blends a foreground over a background image with
+//! per-layer alpha. Indexed images (`L8`, `AL44`, `AL88`, `L4`) use the color lookup
+//! table (CLUT) loaded with [`Dma2d::load_clut`]:
+//!
+//! ```
+//! let mut dma2d = Dma2d::new(dp.DMA2D, &mut rcc);
+//! let mut fb = FrameBuffer::new(&mut framebuffer, ColorMode::RGB565, 480);
+//!
+//! dma2d.fill(&mut fb, Rect::new(0, 0, 480, 272), 0x001f)?;
+//!
+//! dma2d.load_clut(Layer::Foreground, &palette)?;
+//! let icon = Image::new(&icon_pixels, ColorMode::L8, 32).alpha(AlphaMode::Multiply(0x80));
+//! dma2d.blend(&icon, None, &mut fb, Rect::new(10, 10, 32, 32))?;
+//! ```
+//!
+//! Transfers block until they are complete. The input images are read from their first
+//! pixel, to copy a part of an image, slice it at the top left pixel of that part.
+
+use core::{marker::PhantomData, mem};
+
+use enumflags2::BitFlags;
+
+use crate::pac::{DMA2D, RCC};
+use crate::rcc::{Enable, Reset};
+use crate::{ClearFlags, ReadFlags};
+
+/// Maximal number of pixels per line and line offset (14 bits)
+const MAX_LINE: u16 = 0x3fff;
+
+/// CLUT color mode and size bits of the PFC control registers
+const PFCCR_CLUT_MASK: u32 = 0xff10;
+/// CLUT loading start bit of the PFC control registers
+const PFCCR_START: u32 = 1 << 5;
+
+/// Pixel formats of the input images and of the frame buffers
+///
+/// Only the formats up to `ARGB4444` can be written to a [`FrameBuffer`].
+///
+/// Notes :
+/// * `L8`, `L4`: 8-bit and 4-bit CLUT index
+/// * `AL44`: 4-bit alpha + 4-bit CLUT index
+/// * `AL88`: 8-bit alpha + 8-bit CLUT index
+/// * `A8`, `A4`: 8-bit and 4-bit alpha of the layer color set with [`Image::color`]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ColorMode {
+    ARGB8888 = 0b0000,
+    RGB888 = 0b0001,
+    RGB565 = 0b0010,
+    ARGB1555 = 0b0011,
+    ARGB4444 = 0b0100,
+    L8 = 0b0101,
+    AL44 = 0b0110,
+    AL88 = 0b0111,
+    L4 = 0b1000,
+    A8 = 0b1001,
+    A4 = 0b1010,
+}
+
+impl ColorMode {
+    /// Number of bits of a pixel
+    pub const fn bits_per_pixel(self) -> usize {
+        match self {
+            Self::ARGB8888 => 32,
+            Self::RGB888 => 24,
+            Self::RGB565 | Self::ARGB1555 | Self::ARGB4444 | Self::AL88 => 16,
+            Self::L8 | Self::AL44 | Self::A8 => 8,
+            Self::L4 | Self::A4 => 4,
+        }
+    }
+
+    /// The DMA2D can write this format
+    pub const fn is_output(self) -> bool {
+        (self as u8) <= Self::ARGB4444 as u8
+    }
+
+    /// Number of bytes used by `height` lines of `width` pixels, `stride` pixels apart
+    fn bytes(self, stride: u16, width: u16, height: u16) -> usize {
+        let pixels = (height as usize - 1) * stride as usize + width as usize;
+        (pixels * self.bits_per_pixel()).div_ceil(8)
+    }
+}
+
+#[cfg(feature = "ltdc")]
+impl From<crate::ltdc::PixelFormat> for ColorMode {
+    fn from(format: crate::ltdc::PixelFormat) -> Self {
+        use crate::ltdc::PixelFormat;
+        match format {
+            PixelFormat::ARGB8888 => Self::ARGB8888,
+            PixelFormat::RGB888 => Self::RGB888,
+            PixelFormat::RGB565 => Self::RGB565,
+            PixelFormat::ARGB1555 => Self::ARGB1555,
+            PixelFormat::ARGB4444 => Self::ARGB4444,
+            PixelFormat::L8 => Self::L8,
+            PixelFormat::AL44 => Self::AL44,
+            PixelFormat::AL88 => Self::AL88,
+        }
+    }
+}
+
+/// Alpha applied to the pixels of an input layer
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AlphaMode {
+    /// Alpha of the pixels
+    #[default]
+    Keep,
+    /// Replaced by the given alpha
+    Replace(u8),
+    /// Multiplied with the given alpha
+    Multiply(u8),
+}
+
+impl AlphaMode {
+    /// AM and ALPHA bits of the PFC control registers
+    fn bits(self) -> u32 {
+        match self {
+            Self::Keep => 0,
+            Self::Replace(alpha) => 0b01 << 16 | (alpha as u32) << 24,
+            Self::Multiply(alpha) => 0b10 << 16 | (alpha as u32) << 24,
+        }
+    }
+}
+
+/// Input layer of the DMA2D
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Layer {
+    Foreground,
+    Background,
+}
+
+/// Rectangle of a frame buffer
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    pub const fn new(x: u16, y: u16, width: u16, height: u16) -> Self {
+        Self {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+}
+
+/// Input image read by the DMA2D
+pub struct Image<'a> {
+    addr: u32,
+    len: usize,
+    mode: ColorMode,
+    stride: u16,
+    alpha: AlphaMode,
+    color: u32,
+    _data: PhantomData<&'a [u8]>,
+}
+
+impl<'a> Image<'a> {
+    /// Image in `data` with lines of `stride` pixels
+    pub fn new<T: Copy>(data: &'a [T], mode: ColorMode, stride: u16) -> Self {
+        Self {
+            addr: data.as_ptr() as u32,
+            len: mem::size_of_val(data),
+            mode,
+            stride,
+            alpha: AlphaMode::Keep,
+            color: 0,
+            _data: PhantomData,
+        }
+    }
+
+    /// Sets the alpha applied to the pixels
+    pub fn alpha(mut self, alpha: AlphaMode) -> Self {
+        self.alpha = alpha;
+        self
+    }
+
+    /// Sets the RGB888 color of the `A8` and `A4` formats
+    pub fn color(mut self, rgb: u32) -> Self {
+        self.color = rgb & 0xff_ffff;
+        self
+    }
+}
+
+/// Frame buffer written by the DMA2D
+pub struct FrameBuffer<'a> {
+    addr: u32,
+    len: usize,
+    mode: ColorMode,
+    stride: u16,
+    _data: PhantomData<&'a mut [u8]>,
+}
+
+impl<'a> FrameBuffer<'a> {
+    /// Frame buffer in `data` with lines of `stride` pixels
+    ///
+    /// # Panics
+    ///
+    /// * When `mode` can't be written by the DMA2D.
+    pub fn new<T: Copy>(data: &'a mut [T], mode: ColorMode, stride: u16) -> Self {
+        assert!(mode.is_output());
+        Self {
+            addr: data.as_mut_ptr() as u32,
+            len: mem::size_of_val(data),
+            mode,
+            stride,
+            _data: PhantomData,
+        }
+    }
+
+    /// Address of the top left pixel of `area`
+    ///
+    /// # Panics
+    ///
+    /// * When `area` is not inside the frame buffer.
+    fn area_address(&self, area: &Rect) -> u32 {
+        assert!(area.x as usize + area.width as usize <= self.stride as usize);
+        let offset = (area.y as usize * self.stride as usize + area.x as usize)
+            * self.mode.bits_per_pixel()
+            / 8;
+        assert!(offset + self.mode.bytes(self.stride, area.width, area.height) <= self.len);
+        self.addr + offset as u32
+    }
+}
+
+/// DMA2D errors
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// Bus error while reading or writing memory
+    Transfer,
+    /// Invalid configuration, e.g. a misaligned address
+    Configuration,
+    /// The CLUT was accessed while it was loaded
+    ClutAccess,
+}
+
+/// DMA2D interrupt events
+#[enumflags2::bitflags]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[repr(u32)]
+pub enum Event {
+    /// Transfer error interrupt enable
+    TransferError = 1 << 8,
+    /// Transfer complete interrupt enable
+    TransferComplete = 1 << 9,
+    /// Transfer watermark interrupt enable
+    TransferWatermark = 1 << 10,
+    /// CLUT access error interrupt enable
+    ClutAccessError = 1 << 11,
+    /// CLUT transfer complete interrupt enable
+    ClutTransferComplete = 1 << 12,
+    /// Configuration error interrupt enable
+    ConfigurationError = 1 << 13,
+}
+
+/// DMA2D status flags
+#[enumflags2::bitflags]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[repr(u32)]
+pub enum Flag {
+    /// Transfer error
+    TransferError = 1 << 0,
+    /// Transfer complete
+    TransferComplete = 1 << 1,
+    /// Transfer watermark
+    TransferWatermark = 1 << 2,
+    /// CLUT access error
+    ClutAccessError = 1 << 3,
+    /// CLUT transfer complete
+    ClutTransferComplete = 1 << 4,
+    /// Configuration error
+    ConfigurationError = 1 << 5,
+}
+
+/// DMA2D operating modes
+#[derive(Clone, Copy)]
+enum Mode {
+    MemoryToMemory = 0b00,
+    MemoryToMemoryPfc = 0b01,
+    Blending = 0b10,
+    RegisterToMemory = 0b11,
+}
+
+/// A handle to the DMA2D peripheral
+pub struct Dma2d {
+    pub(crate) periph: DMA2D,
+}
+
+impl Dma2d {
+    /// Enables and resets the DMA2D
+    pub fn new(dma2d: DMA2D, rcc: &mut RCC) -> Self {
+        DMA2D::enable(rcc);
+        DMA2D::reset(rcc);
+        Self { periph: dma2d }
+    }
+
+    /// Returns the DMA2D peripheral
+    pub fn release(self) -> DMA2D {
+        self.periph
+    }
+
+    /// Is a transfer or a CLUT loading in progress?
+    pub fn is_busy(&self) -> bool {
+        let cr = self.periph.cr().read();
+        let fg = self.periph.fgpfccr().read();
+        let bg = self.periph.bgpfccr().read();
+        cr.start().bit_is_set() || fg.start().bit_is_set() || bg.start().bit_is_set()
+    }
+
+    /// Fills `area` of `dst` with `color`, blocking until done.
+    ///
+    /// `color` is in the format of the frame buffer, e.g. `0xAARRGGBB` for `ARGB8888`
+    /// or `0bRRRRRGGGGGGBBBBB` for `RGB565`.
+    ///
+    /// # Panics
+    ///
+    /// * When `area` is not inside the frame buffer.
+    pub fn fill(&mut self, dst: &mut FrameBuffer, area: Rect, color: u32) -> Result<(), Error> {
+        if area.width == 0 || area.height == 0 {
+            return Ok(());
+        }
+        self.set_output(dst, &area);
+        unsafe {
+            self.periph.ocolr().write_with_zero(|w| w.bits(color));
+        }
+        self.start(Mode::RegisterToMemory)
+    }
+
+    /// Copies `src` to `area` of `dst`, converting the pixel format if `src` has
+    /// another format or an alpha mode, blocking until done.
+    ///
+    /// # Panics
+    ///
+    /// * When `area` is not inside the frame buffer or larger than `src`.
+    pub fn copy(&mut self, src: &Image, dst: &mut FrameBuffer, area: Rect) -> Result<(), Error> {
+        if area.width == 0 || area.height == 0 {
+            return Ok(());
+        }
+        self.set_output(dst, &area);
+        self.set_input(Layer::Foreground, src, &area);
+        if src.mode == dst.mode && src.alpha == AlphaMode::Keep {
+            self.start(Mode::MemoryToMemory)
+        } else {
+            self.start(Mode::MemoryToMemoryPfc)
+        }
+    }
+
+    /// Blends `fg` over `bg` into `area` of `dst`, blocking until done.
+    ///
+    /// With `bg` set to `None`, `fg` is blended over the content of `area`.
+    ///
+    /// # Panics
+    ///
+    /// * When `area` is not inside the frame buffer or larger than `fg` or `bg`.
+    pub fn blend(
+        &mut self,
+        fg: &Image,
+        bg: Option<&Image>,
+        dst: &mut FrameBuffer,
+        area: Rect,
+    ) -> Result<(), Error> {
+        if area.width == 0 || area.height == 0 {
+            return Ok(());
+        }
+        self.set_output(dst, &area);
+        self.set_input(Layer::Foreground, fg, &area);
+        match bg {
+            Some(bg) => self.set_input(Layer::Background, bg, &area),
+            None => {
+                // The frame buffer is read back as background
+                let addr = dst.area_address(&area);
+                let bg = Image {
+                    addr,
+                    len: dst.len - (addr - dst.addr) as usize,
+                    mode: dst.mode,
+                    stride: dst.stride,
+                    alpha: AlphaMode::Keep,
+                    color: 0,
+                    _data: PhantomData,
+                };
+                self.set_input(Layer::Background, &bg, &area);
+            }
+        }
+        self.start(Mode::Blending)
+    }
+
+    /// Loads ARGB8888 `clut` into the color lookup table of `layer`, blocking until done.
+    ///
+    /// # Panics
+    ///
+    /// * When `clut` is empty or has more than 256 colors.
+    pub fn load_clut(&mut self, layer: Layer, clut: &[u32]) -> Result<(), Error> {
+        assert!(!clut.is_empty() && clut.len() <= 256);
+        self.clear_all_flags();
+        let cmar = clut.as_ptr() as u32;
+        // ARGB8888 CLUT color mode, size and start
+        let pfccr = |bits: u32| {
+            bits & !(PFCCR_CLUT_MASK | PFCCR_START) | (clut.len() as u32 - 1) << 8 | PFCCR_START
+        };
+        match layer {
+            Layer::Foreground => {
+                unsafe {
+                    self.periph.fgcmar().write_with_zero(|w| w.bits(cmar));
+                }
+                self.periph
+                    .fgpfccr()
+                    .modify(|r, w| unsafe { w.bits(pfccr(r.bits())) });
+            }
+            Layer::Background => {
+                unsafe {
+                    self.periph.bgcmar().write_with_zero(|w| w.bits(cmar));
+                }
+                self.periph
+                    .bgpfccr()
+                    .modify(|r, w| unsafe { w.bits(pfccr(r.bits())) });
+            }
+        }
+
+        loop {
+            let flags = self.flags();
+            if flags.contains(Flag::ClutAccessError) {
+                self.clear_all_flags();
+                return Err(Error::ClutAccess);
+            } else if flags.contains(Flag::TransferError) {
+                self.clear_all_flags();
+                return Err(Error::Transfer);
+            } else if flags.contains(Flag::ConfigurationError) {
+                self.clear_all_flags();
+                return Err(Error::Configuration);
+            } else if flags.contains(Flag::ClutTransferComplete) {
+                self.clear_flags(Flag::ClutTransferComplete);
+                return Ok(());
+            }
+        }
+    }
+
+    /// Output format, address, line offset and size
+    fn set_output(&mut self, dst: &FrameBuffer, area: &Rect) {
+        let addr = dst.area_address(area);
+        assert!(area.width <= MAX_LINE && dst.stride - area.width <= MAX_LINE);
+        self.periph
+            .opfccr()
+            .write(|w| unsafe { w.bits(dst.mode as u32) });
+        unsafe {
+            self.periph.omar().write_with_zero(|w| w.bits(addr));
+        }
+        self.periph
+            .oor()
+            .write(|w| unsafe { w.bits((dst.stride - area.width) as u32) });
+        self.periph
+            .nlr()
+            .write(|w| unsafe { w.bits((area.width as u32) << 16 | area.height as u32) });
+    }
+
+    /// Input format, alpha, color, address and line offset of `layer`
+    fn set_input(&mut self, layer: Layer, image: &Image, area: &Rect) {
+        assert!(image.stride >= area.width && image.stride - area.width <= MAX_LINE);
+        assert!(image.mode.bytes(image.stride, area.width, area.height) <= image.len);
+        let offset = (image.stride - area.width) as u32;
+        // The loaded CLUT is kept
+        let pfccr = |bits: u32| bits & PFCCR_CLUT_MASK | image.alpha.bits() | image.mode as u32;
+        match layer {
+            Layer::Foreground => {
+                unsafe {
+                    self.periph.fgmar().write_with_zero(|w| w.bits(image.addr));
+                    self.periph
+                        .fgcolr()
+                        .write_with_zero(|w| w.bits(image.color));
+                }
+                self.periph.fgor().write(|w| unsafe { w.bits(offset) });
+                self.periph
+                    .fgpfccr()
+                    .modify(|r, w| unsafe { w.bits(pfccr(r.bits())) });
+            }
+            Layer::Background => {
+                unsafe {
+                    self.periph.bgmar().write_with_zero(|w| w.bits(image.addr));
+                    self.periph
+                        .bgcolr()
+                        .write_with_zero(|w| w.bits(image.color));
+                }
+                self.periph.bgor().write(|w| unsafe { w.bits(offset) });
+                self.periph
+                    .bgpfccr()
+                    .modify(|r, w| unsafe { w.bits(pfccr(r.bits())) });
+            }
+        }
+    }
+
+    /// Starts the transfer and waits until it is complete
+    fn start(&mut self, mode: Mode) -> Result<(), Error> {
+        self.clear_all_flags();
+        self.periph
+            .cr()
+            .modify(|r, w| unsafe { w.bits(r.bits() & !(0b11 << 16) | (mode as u32) << 16 | 1) });
+
+        loop {
+            let flags = self.flags();
+            if flags.contains(Flag::ConfigurationError) {
+                self.clear_all_flags();
+                return Err(Error::Configuration);
+            } else if flags.contains(Flag::TransferError) {
+                self.clear_all_flags();
+                return Err(Error::Transfer);
+            } else if flags.contains(Flag::TransferComplete) {
+                self.clear_flags(Flag::TransferComplete | Flag::TransferWatermark);
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl crate::Listen for Dma2d {
+    type Event = Event;
+
+    fn listen_event(
+        &mut self,
+        disable: Option<BitFlags<Self::Event>>,
+        enable: Option<BitFlags<Self::Event>>,
+    ) {
+        self.periph.cr().modify(|r, w| unsafe {
+            w.bits({
+                // Don't start a transfer again
+                let mut bits = r.bits() & !1;
+                if let Some(d) = disable {
+                    bits &= !d.bits();
+                }
+                if let Some(e) = enable {
+                    bits |= e.bits();
+                }
+                bits
+            })
+        });
+    }
+}
+
+impl crate::ClearFlags for Dma2d {
+    type Flag = Flag;
+
+    #[inline(always)]
+    fn clear_flags(&mut self, flags: impl Into<BitFlags<Self::Flag>>) {
+        self.periph
+            .ifcr()
+            .write(|w| unsafe { w.bits(flags.into().bits()) });
+    }
+}
+
+impl crate::ReadFlags for Dma2d {
+    type Flag = Flag;
+
+    #[inline(always)]
+    fn flags(&self) -> BitFlags<Self::Flag> {
+        BitFlags::from_bits_truncate(self.periph.isr().read().bits())
+    }
+}
@@ -5,7 +5,90 @@ use embedded_storage::nor_flash::{
 use crate::pac::flash::cr::PSIZE;
 use crate::pac::FLASH;
 use crate::signature::FlashSize;
+#[cfg(feature = "async")]
+use crate::{Listen, OnDrop};
+#[cfg(feature = "async")]
+use core::{future::poll_fn, task::Poll};
 use core::{ptr, slice};
+use enumflags2::BitFlags;
+
+#[cfg(feature = "async")]
+static WAKER: atomic_waker::AtomicWaker = atomic_waker::AtomicWaker::new();
+
+/// Handles the `FLASH` interrupt for the async erase and program operations.
+///
+/// Once the operation is done, disables the end of operation and error interrupts,
+/// sector erase and programming, and wakes the task awaiting them.
+#[cfg(feature = "async")]
+pub fn on_interrupt() {
+    let flash = unsafe { &*FLASH::ptr() };
+    if flash.sr().read().bsy().bit() {
+        return;
+    }
+    flash.cr().modify(|r, w| unsafe {
+        w.bits(r.bits() & !(Event::EndOfOperation as u32 | Event::Error as u32));
+        w.ser().clear_bit();
+        w.pg().clear_bit()
+    });
+    flash
+        .sr()
+        .write(|w| unsafe { w.bits(CFlag::EndOfOperation as u32) });
+    WAKER.wake();
+}
+
+/// Flash interrupt events
+#[enumflags2::bitflags]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[repr(u32)]
+pub enum Event {
+    /// End of operation interrupt enable
+    EndOfOperation = 1 << 24,
+    /// Operation error interrupt enable
+    Error = 1 << 25,
+}
+
+/// Flash status flags
+#[enumflags2::bitflags]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[repr(u32)]
+pub enum Flag {
+    /// End of operation, only set while the interrupt is enabled
+    EndOfOperation = 1 << 0,
+    /// Operation error
+    OperationError = 1 << 1,
+    /// Write protection error
+    WriteProtection = 1 << 4,
+    /// Programming alignment error
+    ProgrammingAlignment = 1 << 5,
+    /// Programming parallelism error
+    ProgrammingParallelism = 1 << 6,
+    /// Programming sequence error
+    ProgrammingSequence = 1 << 7,
+    /// Operation in progress
+    Busy = 1 << 16,
+}
+
+/// Flash clearable flags
+#[enumflags2::bitflags]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[repr(u32)]
+pub enum CFlag {
+    /// End of operation
+    EndOfOperation = 1 << 0,
+    /// Operation error
+    OperationError = 1 << 1,
+    /// Write protection error
+    WriteProtection = 1 << 4,
+    /// Programming alignment error
+    ProgrammingAlignment = 1 << 5,
+    /// Programming parallelism error
+    ProgrammingParallelism = 1 << 6,
+    /// Programming sequence error
+    ProgrammingSequence = 1 << 7,
+}
 
 /// Flash erase/program error
 #[derive(Debug, Clone, Copy)]
@@ -15,6 +98,14 @@ pub enum Error {
     ProgrammingAlignment,
     WriteProtection,
     Operation,
+    /// The option bytes could not be unlocked, a wrong key locks them until the next reset
+    OptionLocked,
+    /// The staged option bytes were rejected without programming them
+    InvalidOptionBytes,
+    /// The OTP block is locked
+    OtpLocked,
+    /// The OTP bytes are already programmed
+    OtpProgrammed,
 }
 
 impl Error {
@@ -55,6 +146,27 @@ pub trait FlashExt {
     fn dual_bank(&self) -> bool;
     /// Returns flash memory sector of a given offset. Returns none if offset is out of range.
     fn sector(&self, offset: usize) -> Option<FlashSector>;
+    /// Reads the current option bytes
+    fn option_bytes(&self) -> OptionBytes;
+    /// Programs the option bytes staged in `options`, blocking until done.
+    ///
+    /// Changes are loaded on the next reset, a read protection change on the next power-on.
+    /// Decreasing the read protection to level 0 mass erases the flash.
+    /// Level 2 is permanent and is rejected with [`Error::InvalidOptionBytes`].
+    fn program_option_bytes(&mut self, options: &OptionBytes) -> Result<(), Error>;
+    /// Returns the one-time programmable area
+    fn otp(&mut self) -> Otp<'_>;
+    /// Returns the offset of `bank`, which depends on the bank swap.
+    /// Returns none for the second bank of single bank flash.
+    fn bank_offset(&self, bank: Bank) -> Option<usize> {
+        if !self.dual_bank() {
+            (bank == Bank::Bank1).then_some(0)
+        } else if (bank == Bank::Bank2) != banks_swapped() {
+            Some(self.len() / 2)
+        } else {
+            Some(0)
+        }
+    }
 }
 
 impl FlashExt for FLASH {
@@ -98,7 +210,114 @@ impl FlashExt for FLASH {
     }
 
     fn sector(&self, offset: usize) -> Option<FlashSector> {
-        flash_sectors(self.len(), self.dual_bank()).find(|s| s.contains(offset))
+        flash_sectors(self.len(), self.dual_bank())
+            .find(|s| s.contains(offset))
+            .map(swapped_sector)
+    }
+
+    fn option_bytes(&self) -> OptionBytes {
+        let optcr = self.optcr().read().bits();
+        let pcrop = !cfg!(feature = "gpio-f417") && optcr & OPTCR_SPRMOD != 0;
+        // nWRP bits are cleared for write protected sectors, set for PCROP sectors
+        let sectors = |nwrp: u32| if pcrop { nwrp } else { !nwrp } & 0xFFF;
+        #[allow(unused_mut)]
+        let mut protected_sectors = sectors(optcr >> 16);
+        #[cfg(any(feature = "gpio-f427", feature = "gpio-f469"))]
+        {
+            protected_sectors |= sectors(self.optcr1().read().bits() >> 16) << 12;
+        }
+        OptionBytes {
+            read_protection: match (optcr >> 8) as u8 {
+                0xAA => ReadProtection::Level0,
+                0xCC => ReadProtection::Level2,
+                _ => ReadProtection::Level1,
+            },
+            bor_level: match (optcr >> 2) & 0b11 {
+                0b00 => BorLevel::Level3,
+                0b01 => BorLevel::Level2,
+                0b10 => BorLevel::Level1,
+                _ => BorLevel::Off,
+            },
+            watchdog_software: optcr & (1 << 5) != 0,
+            reset_on_stop: optcr & (1 << 6) == 0,
+            reset_on_standby: optcr & (1 << 7) == 0,
+            pcrop,
+            protected_sectors,
+        }
+    }
+
+    fn program_option_bytes(&mut self, options: &OptionBytes) -> Result<(), Error> {
+        let sectors = if cfg!(any(feature = "gpio-f427", feature = "gpio-f469")) {
+            24
+        } else {
+            12
+        };
+        let current = self.option_bytes();
+        // PCROP is only left with a regression from read protection level 1 to level 0
+        let pcrop_removed = current.pcrop
+            && !options.pcrop
+            && !(current.read_protection == ReadProtection::Level1
+                && options.read_protection == ReadProtection::Level0);
+        if options.read_protection == ReadProtection::Level2
+            || options.protected_sectors >> sectors != 0
+            || (options.pcrop && cfg!(feature = "gpio-f417"))
+            || pcrop_removed
+        {
+            return Err(Error::InvalidOptionBytes);
+        }
+
+        while self.sr().read().bsy().bit() {}
+        self.optkeyr()
+            .write(|w| unsafe { w.optkeyr().bits(OPT_KEY1) });
+        self.optkeyr()
+            .write(|w| unsafe { w.optkeyr().bits(OPT_KEY2) });
+        if self.optcr().read().optlock().bit() {
+            return Err(Error::OptionLocked);
+        }
+        // Errors of earlier operations
+        self.sr().write(|w| unsafe { w.bits(SR_ERRORS) });
+
+        let rdp: u32 = match options.read_protection {
+            ReadProtection::Level0 => 0xAA,
+            _ => 0x55,
+        };
+        let nwrp = |sectors: u32| {
+            if options.pcrop {
+                sectors
+            } else {
+                !sectors
+            }
+        } & 0xFFF;
+        let bor = options.bor_level as u32;
+        let optcr = (options.pcrop as u32) << 31
+            | nwrp(options.protected_sectors) << 16
+            | rdp << 8
+            | (!options.reset_on_standby as u32) << 7
+            | (!options.reset_on_stop as u32) << 6
+            | (options.watchdog_software as u32) << 5
+            | bor << 2;
+        #[cfg(any(feature = "gpio-f427", feature = "gpio-f469"))]
+        self.optcr1().modify(|r, w| unsafe {
+            w.bits(r.bits() & !(0xFFF << 16) | nwrp(options.protected_sectors >> 12) << 16)
+        });
+        // Other bits (dual bank configuration, ..) are kept
+        let mask = if cfg!(feature = "gpio-f417") {
+            OPTCR_MASK
+        } else {
+            OPTCR_MASK | OPTCR_SPRMOD
+        };
+        self.optcr()
+            .modify(|r, w| unsafe { w.bits(r.bits() & !mask | optcr) });
+        self.optcr().modify(|_, w| w.optstrt().set_bit());
+        while self.sr().read().bsy().bit() {}
+
+        let result = Error::read(self).map(Err).unwrap_or(Ok(()));
+        self.optcr().modify(|_, w| w.optlock().set_bit());
+        result
+    }
+
+    fn otp(&mut self) -> Otp<'_> {
+        Otp { flash: self }
     }
 }
 
@@ -149,6 +368,18 @@ impl FlashExt for LockedFlash {
     fn sector(&self, offset: usize) -> Option<FlashSector> {
         self.flash.sector(offset)
     }
+
+    fn option_bytes(&self) -> OptionBytes {
+        self.flash.option_bytes()
+    }
+
+    fn program_option_bytes(&mut self, options: &OptionBytes) -> Result<(), Error> {
+        self.flash.program_option_bytes(options)
+    }
+
+    fn otp(&mut self) -> Otp<'_> {
+        self.flash.otp()
+    }
 }
 
 /// Result of `FlashExt::unlocked()`
@@ -193,8 +424,22 @@ impl UnlockedFlash<'_> {
     /// Refer to the reference manual to see which sector corresponds
     /// to which memory address.
     pub fn erase(&mut self, sector: u8) -> Result<(), Error> {
+        nb::block!(self.start_erase(sector))?;
+        nb::block!(self.poll())
+    }
+
+    /// Starts erasing a flash sector without waiting, see [`poll`](Self::poll).
+    ///
+    /// Returns `WouldBlock` while a previous operation is ongoing.
+    pub fn start_erase(&mut self, sector: u8) -> nb::Result<(), Error> {
         let snb = if sector < 12 { sector } else { sector + 4 };
 
+        // CR can't be written during an operation
+        if self.flash.sr().read().bsy().bit() {
+            return Err(nb::Error::WouldBlock);
+        }
+        // Errors of earlier operations
+        self.flash.sr().write(|w| unsafe { w.bits(SR_ERRORS) });
         self.flash.cr().modify(|_, w| {
             // start
             w.strt().set_bit();
@@ -208,8 +453,117 @@ impl UnlockedFlash<'_> {
             // no programming
             w.pg().clear_bit()
         });
-        self.wait_ready();
-        self.ok()
+        Ok(())
+    }
+
+    /// Starts programming bytes with offset into flash memory without waiting,
+    /// up to the next 16 byte boundary. Returns the number of bytes written,
+    /// see [`poll`](Self::poll).
+    ///
+    /// Returns `WouldBlock` while a previous operation is ongoing.
+    /// The bytes are programmed one at a time with the x8 parallelism, the CPU stalls
+    /// while writing a byte until the previous one is programmed.
+    pub fn start_program(&mut self, offset: usize, bytes: &[u8]) -> nb::Result<usize, Error> {
+        let ptr = self.flash.address() as *mut u8;
+        let count = bytes.len().min(16 - offset % 16);
+
+        if self.flash.sr().read().bsy().bit() {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.flash.sr().write(|w| unsafe { w.bits(SR_ERRORS) });
+        self.flash.cr().modify(|_, w| {
+            w.psize().variant(PSIZE::Psize8);
+            // no sector erase
+            w.ser().clear_bit();
+            // programming
+            w.pg().set_bit()
+        });
+        for (i, byte) in bytes[..count].iter().enumerate() {
+            unsafe {
+                ptr::write_volatile(ptr.add(offset + i), *byte);
+            }
+        }
+        Ok(count)
+    }
+
+    /// Checks if the started erase or program operation is done.
+    ///
+    /// Once done, erase and programming are disabled again, also on errors.
+    pub fn poll(&mut self) -> nb::Result<(), Error> {
+        if self.flash.sr().read().bsy().bit() {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.flash.cr().modify(|r, w| unsafe {
+            w.bits(r.bits() & !(CR_MER | CR_MER1));
+            w.ser().clear_bit();
+            w.pg().clear_bit()
+        });
+        self.flash
+            .sr()
+            .write(|w| unsafe { w.bits(CFlag::EndOfOperation as u32) });
+        self.ok().map_err(nb::Error::Other)
+    }
+
+    /// Erase a flash sector, waiting for the flash interrupt.
+    #[cfg(feature = "async")]
+    pub async fn erase_async(&mut self, sector: u8) -> Result<(), Error> {
+        // Stops the erase if the future is dropped, `on_interrupt` does it if still ongoing
+        let _guard = OnDrop::new((), |_| on_interrupt());
+        self.wait_idle().await;
+        self.listen(Event::EndOfOperation | Event::Error);
+        nb::block!(self.start_erase(sector))?;
+        self.wait_async().await
+    }
+
+    /// Program bytes with offset into flash memory, waiting for the flash interrupt
+    /// after each 16 bytes.
+    #[cfg(feature = "async")]
+    pub async fn program_async(
+        &mut self,
+        mut offset: usize,
+        mut bytes: &[u8],
+    ) -> Result<(), Error> {
+        // Stops programming if the future is dropped, `on_interrupt` does it if still ongoing
+        let _guard = OnDrop::new((), |_| on_interrupt());
+        self.wait_idle().await;
+        while !bytes.is_empty() {
+            self.listen(Event::EndOfOperation | Event::Error);
+            let count = nb::block!(self.start_program(offset, bytes))?;
+            self.wait_async().await?;
+            offset += count;
+            bytes = &bytes[count..];
+        }
+        Ok(())
+    }
+
+    /// Waits for an operation started before, as CR can't be written during it.
+    /// It may have been started without the interrupts, so the task is woken right away.
+    #[cfg(feature = "async")]
+    async fn wait_idle(&self) {
+        poll_fn(|cx| {
+            if self.flash.sr().read().bsy().bit() {
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            } else {
+                Poll::Ready(())
+            }
+        })
+        .await
+    }
+
+    /// Waits for the end of the operation started with the interrupts enabled,
+    /// they are disabled by `on_interrupt`
+    #[cfg(feature = "async")]
+    async fn wait_async(&mut self) -> Result<(), Error> {
+        poll_fn(|cx| {
+            WAKER.register(cx.waker());
+            match self.poll() {
+                Err(nb::Error::WouldBlock) => Poll::Pending,
+                Err(nb::Error::Other(e)) => Poll::Ready(Err(e)),
+                Ok(()) => Poll::Ready(Ok(())),
+            }
+        })
+        .await
     }
 
     /// Program bytes with offset into flash memory,
@@ -251,6 +605,81 @@ impl UnlockedFlash<'_> {
         Ok(())
     }
 
+    /// Mass erase a flash bank, blocking until done.
+    ///
+    /// Only code fetched from the other bank or from RAM keeps running, such as interrupt
+    /// handlers, while reads of the erased bank stall until done. Use
+    /// [`start_erase_bank`](Self::start_erase_bank) to keep running the caller.
+    ///
+    /// # Panics
+    ///
+    /// * When erasing the second bank of single bank flash.
+    pub fn erase_bank(&mut self, bank: Bank) -> Result<(), Error> {
+        nb::block!(self.start_erase_bank(bank))?;
+        nb::block!(self.poll())
+    }
+
+    /// Starts mass erasing a flash bank without waiting, see [`poll`](Self::poll).
+    ///
+    /// Returns `WouldBlock` while a previous operation is ongoing.
+    ///
+    /// # Panics
+    ///
+    /// * When erasing the second bank of single bank flash.
+    pub fn start_erase_bank(&mut self, bank: Bank) -> nb::Result<(), Error> {
+        let mer = match bank {
+            Bank::Bank1 => CR_MER,
+            Bank::Bank2 => {
+                assert!(self.flash.dual_bank());
+                CR_MER1
+            }
+        };
+
+        if self.flash.sr().read().bsy().bit() {
+            return Err(nb::Error::WouldBlock);
+        }
+        self.flash.sr().write(|w| unsafe { w.bits(SR_ERRORS) });
+        self.flash.cr().modify(|r, w| unsafe {
+            w.bits(r.bits() | mer);
+            w.psize().variant(PSIZE::Psize8);
+            // no sector erase
+            w.ser().clear_bit();
+            // no programming
+            w.pg().clear_bit()
+        });
+        self.flash.cr().modify(|_, w| w.strt().set_bit());
+        Ok(())
+    }
+
+    /// Program bytes with offset from the start of a flash bank, blocking until done.
+    ///
+    /// Only code fetched from the other bank or from RAM keeps running while programming,
+    /// which allows a firmware to write its update into the other bank. Use
+    /// [`start_program`](Self::start_program) at [`FlashExt::bank_offset`] to keep running
+    /// the caller.
+    ///
+    /// # Panics
+    ///
+    /// * When programming the second bank of single bank flash.
+    /// * When the bytes exceed the bank.
+    pub fn program_bank(&mut self, bank: Bank, offset: usize, bytes: &[u8]) -> Result<(), Error> {
+        let bank_size = if self.flash.dual_bank() {
+            self.flash.len() / 2
+        } else {
+            self.flash.len()
+        };
+        assert!(offset + bytes.len() <= bank_size);
+        let mut offset = self.flash.bank_offset(bank).unwrap() + offset;
+        let mut bytes = bytes;
+        while !bytes.is_empty() {
+            let count = nb::block!(self.start_program(offset, bytes))?;
+            nb::block!(self.poll())?;
+            offset += count;
+            bytes = &bytes[count..];
+        }
+        Ok(())
+    }
+
     fn ok(&self) -> Result<(), Error> {
         Error::read(self.flash).map(Err).unwrap_or(Ok(()))
     }
@@ -260,6 +689,50 @@ impl UnlockedFlash<'_> {
     }
 }
 
+impl crate::Listen for UnlockedFlash<'_> {
+    type Event = Event;
+
+    /// Writes CR, which stalls the CPU until the current operation is done
+    fn listen_event(
+        &mut self,
+        disable: Option<BitFlags<Self::Event>>,
+        enable: Option<BitFlags<Self::Event>>,
+    ) {
+        self.flash.cr().modify(|r, w| unsafe {
+            w.bits({
+                let mut bits = r.bits();
+                if let Some(d) = disable {
+                    bits &= !d.bits();
+                }
+                if let Some(e) = enable {
+                    bits |= e.bits();
+                }
+                bits
+            })
+        });
+    }
+}
+
+impl crate::ClearFlags for UnlockedFlash<'_> {
+    type Flag = CFlag;
+
+    #[inline(always)]
+    fn clear_flags(&mut self, flags: impl Into<BitFlags<Self::Flag>>) {
+        self.flash
+            .sr()
+            .write(|w| unsafe { w.bits(flags.into().bits()) });
+    }
+}
+
+impl crate::ReadFlags for UnlockedFlash<'_> {
+    type Flag = Flag;
+
+    #[inline(always)]
+    fn flags(&self) -> BitFlags<Self::Flag> {
+        BitFlags::from_bits_truncate(self.flash.sr().read().bits())
+    }
+}
+
 const UNLOCK_KEY1: u32 = 0x45670123;
 const UNLOCK_KEY2: u32 = 0xCDEF89AB;
 
@@ -273,6 +746,232 @@ fn lock(flash: &FLASH) {
     flash.cr().modify(|_, w| w.lock().set_bit());
 }
 
+/// Mass erase of the first bank, or of the whole single bank flash
+const CR_MER: u32 = 1 << 2;
+/// Mass erase of the second bank
+const CR_MER1: u32 = 1 << 15;
+
+/// Flash memory bank
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bank {
+    /// Sectors 0 to 11
+    Bank1,
+    /// Sectors 12 to 23
+    Bank2,
+}
+
+/// Returns true if the second bank is mapped at the flash address,
+/// see `SysCfg::set_flash_bank_swap`
+fn banks_swapped() -> bool {
+    #[cfg(any(feature = "gpio-f427", feature = "gpio-f469"))]
+    {
+        let syscfg = unsafe { &*crate::pac::SYSCFG::ptr() };
+        syscfg.memrmp().read().bits() & (1 << 8) != 0
+    }
+    #[cfg(not(any(feature = "gpio-f427", feature = "gpio-f469")))]
+    {
+        false
+    }
+}
+
+/// Sector numbers of the banks are exchanged with the memory mapping
+fn swapped_sector(mut sector: FlashSector) -> FlashSector {
+    if banks_swapped() {
+        sector.number = if sector.number < 12 {
+            sector.number + 12
+        } else {
+            sector.number - 12
+        };
+    }
+    sector
+}
+
+const OPT_KEY1: u32 = 0x08192A3B;
+const OPT_KEY2: u32 = 0x4C5D6E7F;
+
+/// Error flags of SR, written to clear them
+const SR_ERRORS: u32 = 0xF2;
+/// Bits of OPTCR written by [`FlashExt::program_option_bytes`]
+const OPTCR_MASK: u32 = 0x0FFF_FFEC;
+/// Selection of PCROP instead of write protection by the nWRP bits
+const OPTCR_SPRMOD: u32 = 1 << 31;
+
+/// Read-out protection level
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReadProtection {
+    /// No protection
+    Level0,
+    /// Flash can't be read by the debugger or from RAM and system memory boots
+    Level1,
+    /// Level 1 with the debug interface disabled, permanently
+    Level2,
+}
+
+/// Brown-out reset threshold
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum BorLevel {
+    /// Reset threshold 2.70 - 3.60 V
+    Level3 = 0b00,
+    /// Reset threshold 2.40 - 2.70 V
+    Level2 = 0b01,
+    /// Reset threshold 2.10 - 2.40 V
+    Level1 = 0b10,
+    /// Only power-on/power-down reset, 1.80 - 2.10 V
+    Off = 0b11,
+}
+
+/// Option bytes
+///
+/// Read with [`FlashExt::option_bytes`], modified and programmed with
+/// [`FlashExt::program_option_bytes`]:
+///
+/// ```
+/// let mut options = flash.option_bytes();
+/// options.read_protection = ReadProtection::Level1;
+/// // Write protected bootloader in sectors 0 and 1
+/// options.protected_sectors |= 0b11;
+/// flash.program_option_bytes(&options)?;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct OptionBytes {
+    /// Read-out protection level
+    pub read_protection: ReadProtection,
+    /// Brown-out reset level
+    pub bor_level: BorLevel,
+    /// The independent watchdog is started by software, otherwise it runs from reset.
+    ///
+    /// The window watchdog has no hardware start option on the STM32F4.
+    pub watchdog_software: bool,
+    /// Entering Stop mode generates a reset
+    pub reset_on_stop: bool,
+    /// Entering Standby mode generates a reset
+    pub reset_on_standby: bool,
+    /// The protected sectors are read protected (PCROP), they can only be executed.
+    /// Otherwise they are write protected.
+    ///
+    /// Not available on STM32F405/407/415/417. Once enabled, it can only be disabled
+    /// together with a regression from read protection level 1 to level 0, which erases
+    /// the flash. Programming other changes is rejected with [`Error::InvalidOptionBytes`].
+    pub pcrop: bool,
+    /// Write or PCROP protected sectors, bit `n` for sector `n`.
+    ///
+    /// Sectors 12 to 23 of the second bank are bits 12 to 23.
+    pub protected_sectors: u32,
+}
+
+/// OTP area address
+const OTP_ADDRESS: usize = 0x1FFF_7800;
+/// OTP lock bytes address, one byte per block
+const OTP_LOCK_ADDRESS: usize = 0x1FFF_7A00;
+/// OTP area size in bytes
+pub const OTP_SIZE: usize = 512;
+/// OTP block size in bytes
+pub const OTP_BLOCK_SIZE: usize = 32;
+/// Number of OTP blocks
+pub const OTP_BLOCKS: usize = OTP_SIZE / OTP_BLOCK_SIZE;
+
+/// One-time programmable area, result of `FlashExt::otp()`
+///
+/// 512 bytes in 16 blocks of 32 bytes. Bytes can be programmed once and
+/// each block can be locked against further programming, which is irreversible.
+///
+/// # Examples
+///
+/// ```
+/// use stm32f4xx_hal::pac::Peripherals;
+/// use stm32f4xx_hal::flash::{FlashExt, LockedFlash};
+/// use embedded_storage::nor_flash::ReadNorFlash;
+///
+/// let dp = Peripherals::take().unwrap();
+/// let mut flash = LockedFlash::new(dp.FLASH);
+/// let mut otp = flash.otp();
+///
+/// // Serial number in the first block
+/// otp.program(0, &serial.to_le_bytes()).unwrap();
+/// otp.lock_block(0).unwrap();
+///
+/// let mut buf = [0u8; 4];
+/// ReadNorFlash::read(&mut otp, 0, &mut buf).unwrap();
+/// ```
+pub struct Otp<'a> {
+    flash: &'a mut FLASH,
+}
+
+impl Otp<'_> {
+    fn data(&self) -> &[u8] {
+        unsafe { slice::from_raw_parts(OTP_ADDRESS as *const u8, OTP_SIZE) }
+    }
+
+    /// Returns true if `block` is locked
+    ///
+    /// # Panics
+    ///
+    /// * When `block` is not less than [`OTP_BLOCKS`].
+    pub fn is_locked(&self, block: usize) -> bool {
+        assert!(block < OTP_BLOCKS);
+        unsafe { ptr::read_volatile((OTP_LOCK_ADDRESS + block) as *const u8) != 0xFF }
+    }
+
+    /// Programs `bytes` at `offset` of the OTP area.
+    ///
+    /// The bytes must not be programmed yet and their blocks must not be locked,
+    /// nothing is written otherwise.
+    ///
+    /// # Panics
+    ///
+    /// * When the bytes exceed the OTP area.
+    pub fn program(&mut self, offset: usize, bytes: &[u8]) -> Result<(), Error> {
+        let end = offset + bytes.len();
+        assert!(end <= OTP_SIZE);
+        if (offset / OTP_BLOCK_SIZE..end.div_ceil(OTP_BLOCK_SIZE)).any(|b| self.is_locked(b)) {
+            return Err(Error::OtpLocked);
+        }
+        if self.data()[offset..end].iter().any(|&b| b != 0xFF) {
+            return Err(Error::OtpProgrammed);
+        }
+        self.write(OTP_ADDRESS + offset, bytes)
+    }
+
+    /// Locks `block` against programming, permanently
+    ///
+    /// # Panics
+    ///
+    /// * When `block` is not less than [`OTP_BLOCKS`].
+    pub fn lock_block(&mut self, block: usize) -> Result<(), Error> {
+        if self.is_locked(block) {
+            return Ok(());
+        }
+        self.write(OTP_LOCK_ADDRESS + block, &[0x00])
+    }
+
+    /// Programs `bytes` at `address` byte by byte
+    fn write(&mut self, address: usize, bytes: &[u8]) -> Result<(), Error> {
+        unlock(self.flash);
+        // Errors of earlier operations
+        self.flash.sr().write(|w| unsafe { w.bits(SR_ERRORS) });
+        self.flash.cr().modify(|_, w| {
+            w.psize().variant(PSIZE::Psize8);
+            w.ser().clear_bit();
+            w.pg().set_bit()
+        });
+        let mut result = Ok(());
+        for (i, byte) in bytes.iter().enumerate() {
+            unsafe {
+                ptr::write_volatile((address + i) as *mut u8, *byte);
+            }
+            while self.flash.sr().read().bsy().bit() {}
+            if let Some(error) = Error::read(self.flash) {
+                result = Err(error);
+                break;
+            }
+        }
+        self.flash.cr().modify(|_, w| w.pg().clear_bit());
+        lock(self.flash);
+        result
+    }
+}
+
 /// Flash memory sector
 pub struct FlashSector {
     /// Sector number
@@ -371,6 +1070,10 @@ impl ErrorType for UnlockedFlash<'_> {
     type Error = Error;
 }
 
+impl ErrorType for Otp<'_> {
+    type Error = Error;
+}
+
 impl ReadNorFlash for LockedFlash {
     const READ_SIZE: usize = 1;
 
@@ -399,6 +1102,20 @@ impl<'a> ReadNorFlash for UnlockedFlash<'a> {
     }
 }
 
+impl ReadNorFlash for Otp<'_> {
+    const READ_SIZE: usize = 1;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let offset = offset as usize;
+        bytes.copy_from_slice(&self.data()[offset..offset + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        OTP_SIZE
+    }
+}
+
 impl<'a> NorFlash for UnlockedFlash<'a> {
     const WRITE_SIZE: usize = 1;
 
@@ -408,7 +1125,7 @@ impl<'a> NorFlash for UnlockedFlash<'a> {
     fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
         let mut current = from as usize;
 
-        for sector in flash_sectors(self.flash.len(), self.flash.dual_bank()) {
+        for sector in flash_sectors(self.flash.len(), self.flash.dual_bank()).map(swapped_sector) {
             if sector.contains(current) {
                 UnlockedFlash::erase(self, sector.number)?;
                 current += sector.size;
@@ -429,3 +1146,48 @@ impl<'a> NorFlash for UnlockedFlash<'a> {
 
 // STM32F4 supports multiple writes
 impl<'a> MultiwriteNorFlash for UnlockedFlash<'a> {}
+
+#[cfg(feature = "async")]
+impl embedded_storage_async::nor_flash::ReadNorFlash for UnlockedFlash<'_> {
+    const READ_SIZE: usize = 1;
+
+    async fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        ReadNorFlash::read(self, offset, bytes)
+    }
+
+    fn capacity(&self) -> usize {
+        self.flash.len()
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_storage_async::nor_flash::NorFlash for UnlockedFlash<'_> {
+    const WRITE_SIZE: usize = 1;
+
+    // Use largest sector size of 128 KB. All smaller sectors will be erased together.
+    const ERASE_SIZE: usize = 128 * 1024;
+
+    async fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        let mut current = from as usize;
+
+        for sector in flash_sectors(self.flash.len(), self.flash.dual_bank()).map(swapped_sector) {
+            if sector.contains(current) {
+                self.erase_async(sector.number).await?;
+                current += sector.size;
+            }
+
+            if current >= to as usize {
+                break;
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        self.program_async(offset as usize, bytes).await
+    }
+}
+
+#[cfg(feature = "async")]
+impl embedded_storage_async::nor_flash::MultiwriteNorFlash for UnlockedFlash<'_> {}
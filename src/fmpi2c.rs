@@ -19,6 +19,8 @@ pub use Mode as FmpMode;
 mod hal_02;
 #[path = "i2c/hal_1.rs"]
 mod hal_1;
+#[cfg(feature = "async")]
+mod hal_async;
 
 type I2cSel = rcc::dckcfgr2::FMPI2C1SEL;
 
@@ -26,6 +28,11 @@ pub trait Instance:
     crate::rcc::Instance + crate::Ptr<RB = i2c1::RegisterBlock> + gpio::alt::I2cCommon
 {
     fn set_clock_source(rcc: &rcc::RegisterBlock, source: I2cSel);
+
+    /// Waker of the task awaiting this I2C.
+    #[cfg(feature = "async")]
+    #[doc(hidden)]
+    fn waker() -> &'static atomic_waker::AtomicWaker;
 }
 
 macro_rules! i2c {
@@ -36,6 +43,12 @@ macro_rules! i2c {
             fn set_clock_source(rcc: &rcc::RegisterBlock, source: I2cSel) {
                 rcc.dckcfgr2().modify(|_, w| w.$i2csel().variant(source));
             }
+
+            #[cfg(feature = "async")]
+            fn waker() -> &'static atomic_waker::AtomicWaker {
+                static WAKER: atomic_waker::AtomicWaker = atomic_waker::AtomicWaker::new();
+                &WAKER
+            }
         }
     };
 }
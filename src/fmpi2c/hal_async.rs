@@ -0,0 +1,252 @@
+//! Async FMPI2C master driven by the event and error interrupts
+//!
+//! [`I2c::on_interrupt`] must be called from both the `FMPI2C1_EV` and `FMPI2C1_ER`
+//! interrupt handlers.
+
+use super::{Address, Error, I2c, Instance, NoAcknowledgeSource};
+use crate::pac::fmpi2c1 as i2c1;
+use crate::OnDrop;
+use core::{future::poll_fn, task::Poll};
+use embedded_hal::i2c::{Operation, SevenBitAddress, TenBitAddress};
+
+const TXIE: u32 = 1 << 1;
+const RXIE: u32 = 1 << 2;
+const NACKIE: u32 = 1 << 4;
+const STOPIE: u32 = 1 << 5;
+const TCIE: u32 = 1 << 6;
+const ERRIE: u32 = 1 << 7;
+const ALL_INTERRUPTS: u32 = TXIE | RXIE | NACKIE | STOPIE | TCIE | ERRIE;
+
+// NBYTES is 8 bits wide, longer transfers are split using RELOAD
+const MAX_CHUNK: usize = 255;
+
+fn disable_interrupts(i2c: &i2c1::RegisterBlock) {
+    i2c.cr1()
+        .modify(|r, w| unsafe { w.bits(r.bits() & !ALL_INTERRUPTS) });
+}
+
+impl<I2C: Instance> I2c<I2C> {
+    /// Handles the `FMPI2Cx_EV` and `FMPI2Cx_ER` interrupts for async transactions.
+    ///
+    /// Disables the I2C interrupts and wakes the task awaiting the I2C,
+    /// which checks the status flags and enables the interrupts again if it has to wait.
+    pub fn on_interrupt() {
+        disable_interrupts(unsafe { &*I2C::ptr() });
+        I2C::waker().wake();
+    }
+
+    /// Waits until `f` returns `true` for the status register or an error flag is set.
+    ///
+    /// `interrupts` are the CR1 interrupt enable bits of the awaited flags.
+    async fn wait_for_isr(
+        &self,
+        interrupts: u32,
+        f: impl Fn(&i2c1::isr::R) -> bool,
+    ) -> Result<(), Error> {
+        poll_fn(|cx| {
+            I2C::waker().register(cx.waker());
+            let isr = self.i2c.isr().read();
+            if isr.nackf().bit_is_set() {
+                // A STOP condition is generated automatically after the NACK
+                self.i2c
+                    .icr()
+                    .write(|w| w.stopcf().clear_bit_by_one().nackcf().clear_bit_by_one());
+                Poll::Ready(Err(Error::NoAcknowledge(NoAcknowledgeSource::Unknown)))
+            } else if isr.arlo().bit_is_set() {
+                self.i2c.icr().write(|w| w.arlocf().clear_bit_by_one());
+                Poll::Ready(Err(Error::ArbitrationLoss))
+            } else if isr.berr().bit_is_set() {
+                self.i2c.icr().write(|w| w.berrcf().clear_bit_by_one());
+                Poll::Ready(Err(Error::Bus))
+            } else if isr.ovr().bit_is_set() {
+                self.i2c.icr().write(|w| w.ovrcf().clear_bit_by_one());
+                Poll::Ready(Err(Error::Overrun))
+            } else if f(&isr) {
+                Poll::Ready(Ok(()))
+            } else {
+                self.i2c
+                    .cr1()
+                    .modify(|r, w| unsafe { w.bits(r.bits() | interrupts | NACKIE | ERRIE) });
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    /// Sets the size of the next chunk, returns the number of bytes after it
+    fn set_chunk(&self, remaining: usize, last: bool) -> usize {
+        let chunk = remaining.min(MAX_CHUNK);
+        let reload = remaining > MAX_CHUNK;
+        self.i2c.cr2().modify(|_, w| {
+            w.nbytes().set(chunk as u8);
+            w.reload().bit(reload);
+            w.autoend().bit(last && !reload)
+        });
+        remaining - chunk
+    }
+
+    /// Transfers adjacent operations of the same type, started with one (repeated) START
+    async fn transfer_group(
+        &self,
+        addr: Address,
+        operations: &mut [Operation<'_>],
+        first_transaction: bool,
+        last: bool,
+    ) -> Result<(), Error> {
+        let read = matches!(operations.first(), Some(Operation::Read(_)));
+        let total: usize = operations
+            .iter()
+            .map(|op| match op {
+                Operation::Read(buffer) => buffer.len(),
+                Operation::Write(bytes) => bytes.len(),
+            })
+            .sum();
+
+        // Set up the address and the first chunk, then send a START condition
+        self.i2c.cr2().modify(|_, w| {
+            match addr {
+                Address::Seven(addr) => {
+                    w.add10().clear_bit();
+                    w.sadd().set(u16::from(addr) << 1);
+                }
+                Address::Ten(addr) => {
+                    w.add10().set_bit();
+                    w.head10r().bit(read && !first_transaction);
+                    w.sadd().set(addr);
+                }
+            }
+            w.rd_wrn().bit(read)
+        });
+        let mut remaining = self.set_chunk(total, last);
+        let mut in_chunk = total - remaining;
+        self.i2c.cr2().modify(|_, w| w.start().set_bit());
+
+        let mut source = NoAcknowledgeSource::Address;
+        for op in operations {
+            let len = match op {
+                Operation::Read(buffer) => buffer.len(),
+                Operation::Write(bytes) => bytes.len(),
+            };
+            for i in 0..len {
+                if in_chunk == 0 {
+                    self.wait_for_isr(TCIE, |isr| isr.tcr().bit_is_set())
+                        .await?;
+                    let next = self.set_chunk(remaining, last);
+                    in_chunk = remaining - next;
+                    remaining = next;
+                }
+
+                match op {
+                    Operation::Read(buffer) => {
+                        self.wait_for_isr(RXIE, |isr| isr.rxne().bit_is_set())
+                            .await?;
+                        buffer[i] = self.i2c.rxdr().read().bits() as u8;
+                    }
+                    Operation::Write(bytes) => {
+                        self.wait_for_isr(TXIE, |isr| isr.txis().bit_is_set())
+                            .await
+                            .map_err(|e| match e {
+                                Error::NoAcknowledge(_) => Error::NoAcknowledge(source),
+                                e => e,
+                            })?;
+                        self.i2c
+                            .txdr()
+                            .write(|w| unsafe { w.bits(u32::from(bytes[i])) });
+                    }
+                }
+                in_chunk -= 1;
+                source = NoAcknowledgeSource::Data;
+            }
+        }
+
+        let result = if last {
+            // AUTOEND generates the STOP condition
+            self.wait_for_isr(STOPIE, |isr| isr.stopf().bit_is_set())
+                .await
+                .map(|_| {
+                    self.i2c.icr().write(|w| w.stopcf().clear_bit_by_one());
+                })
+        } else {
+            self.wait_for_isr(TCIE, |isr| isr.tc().bit_is_set()).await
+        };
+        result.map_err(|e| match e {
+            Error::NoAcknowledge(_) => Error::NoAcknowledge(source),
+            e => e,
+        })
+    }
+
+    async fn transaction_async(
+        &mut self,
+        addr: Address,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Error> {
+        if operations
+            .iter()
+            .any(|op| matches!(op, Operation::Read(buffer) if buffer.is_empty()))
+        {
+            return Err(Error::Overrun);
+        }
+
+        // Clear flags of a previous transaction
+        self.i2c.icr().write(|w| {
+            w.stopcf().clear_bit_by_one();
+            w.nackcf().clear_bit_by_one();
+            w.berrcf().clear_bit_by_one();
+            w.arlocf().clear_bit_by_one();
+            w.ovrcf().clear_bit_by_one()
+        });
+
+        // Release the bus if the transaction is cancelled
+        let guard = OnDrop::new((), |_| {
+            disable_interrupts(&self.i2c);
+            self.i2c.cr2().modify(|_, w| w.stop().set_bit());
+        });
+
+        let mut result = Ok(());
+        let mut first_transaction = true;
+        let mut rest = operations;
+        while !rest.is_empty() {
+            let read = matches!(rest[0], Operation::Read(_));
+            // Adjacent operations of the same type are merged, no repeated START between them
+            let len = rest
+                .iter()
+                .position(|op| matches!(op, Operation::Read(_)) != read)
+                .unwrap_or(rest.len());
+            let (group, tail) = core::mem::take(&mut rest).split_at_mut(len);
+            result = self
+                .transfer_group(addr, group, first_transaction, tail.is_empty())
+                .await;
+            if result.is_err() {
+                break;
+            }
+            first_transaction = false;
+            rest = tail;
+        }
+        guard.defuse();
+
+        disable_interrupts(&self.i2c);
+        result
+    }
+}
+
+impl<I2C: Instance> embedded_hal_async::i2c::I2c<SevenBitAddress> for I2c<I2C> {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.transaction_async(Address::Seven(address), operations)
+            .await
+    }
+}
+
+impl<I2C: Instance> embedded_hal_async::i2c::I2c<TenBitAddress> for I2c<I2C> {
+    async fn transaction(
+        &mut self,
+        address: u16,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.transaction_async(Address::Ten(address), operations)
+            .await
+    }
+}
@@ -70,6 +70,10 @@ mod dynamic;
 pub use dynamic::{Dynamic, DynamicPin};
 mod hal_02;
 mod hal_1;
+#[cfg(feature = "async")]
+mod hal_async;
+#[cfg(feature = "async")]
+pub use hal_async::{on_exti_interrupt, ExtiInput};
 pub mod outport;
 
 pub use embedded_hal_02::digital::v2::PinState;
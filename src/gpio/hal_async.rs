@@ -0,0 +1,223 @@
+//! Async GPIO input waiting for edges and levels with the EXTI line of the pin
+//!
+//! [`on_exti_interrupt`] must be called from the interrupt handlers of the used lines,
+//! including the shared `EXTI9_5` and `EXTI15_10` handlers:
+//!
+//! ```
+//! let mut button = ExtiInput::new(gpioa.pa0.into_pull_down_input(), &mut syscfg, &mut dp.EXTI);
+//! button.wait_for_rising_edge().await;
+//!
+//! #[interrupt]
+//! fn EXTI0() {
+//!     gpio::on_exti_interrupt();
+//! }
+//! ```
+
+use core::{
+    convert::Infallible,
+    future::poll_fn,
+    sync::atomic::{AtomicU32, Ordering},
+    task::Poll,
+};
+
+use atomic_waker::AtomicWaker;
+
+use super::{Edge, ExtiPin, Input, Pin};
+use crate::{pac::EXTI, syscfg::SysCfg};
+
+const LINES: usize = 16;
+
+static WAKERS: [AtomicWaker; LINES] = [const { AtomicWaker::new() }; LINES];
+/// EXTI lines owned by an [`ExtiInput`]
+static ASYNC_LINES: AtomicU32 = AtomicU32::new(0);
+/// Lines triggered since the task awaiting them last checked
+static TRIGGERED: AtomicU32 = AtomicU32::new(0);
+
+/// Handles the `EXTIx` interrupts of [`ExtiInput`]s.
+///
+/// Clears the pending bits of the triggered lines owned by an `ExtiInput` and wakes
+/// the tasks awaiting them. Pending lines used without `ExtiInput` are left untouched,
+/// so this can be called from a shared handler together with other code.
+pub fn on_exti_interrupt() {
+    let exti = unsafe { &*EXTI::ptr() };
+    let pending = exti.pr().read().bits() & ASYNC_LINES.load(Ordering::Relaxed);
+    if pending == 0 {
+        return;
+    }
+    // Write 1 to clear
+    exti.pr().write(|w| unsafe { w.bits(pending) });
+    TRIGGERED.fetch_or(pending, Ordering::Release);
+    for (line, waker) in WAKERS.iter().enumerate() {
+        if pending & (1 << line) != 0 {
+            waker.wake();
+        }
+    }
+}
+
+/// Input pin owning its EXTI line, implements [`embedded_hal_async::digital::Wait`]
+pub struct ExtiInput<const P: char, const N: u8> {
+    pin: Pin<P, N, Input>,
+}
+
+impl<const P: char, const N: u8> ExtiInput<P, N> {
+    const MASK: u32 = 1 << N;
+
+    /// Connects the EXTI line `N` to `pin`, the line is masked until the pin is awaited
+    pub fn new(mut pin: Pin<P, N, Input>, syscfg: &mut SysCfg, exti: &mut EXTI) -> Self {
+        pin.disable_interrupt(exti);
+        pin.make_interrupt_source(syscfg);
+        pin.clear_interrupt_pending_bit();
+        ASYNC_LINES.fetch_or(Self::MASK, Ordering::Relaxed);
+        Self { pin }
+    }
+
+    /// Masks the EXTI line and returns the pin
+    pub fn release(mut self, exti: &mut EXTI) -> Pin<P, N, Input> {
+        self.pin.disable_interrupt(exti);
+        ASYNC_LINES.fetch_and(!Self::MASK, Ordering::Relaxed);
+        self.pin.clear_interrupt_pending_bit();
+        self.pin
+    }
+
+    /// Is the input pin high?
+    #[inline(always)]
+    pub fn is_high(&self) -> bool {
+        self.pin.is_high()
+    }
+
+    /// Is the input pin low?
+    #[inline(always)]
+    pub fn is_low(&self) -> bool {
+        self.pin.is_low()
+    }
+
+    /// Selects the trigger edge and unmasks the line, discarding earlier events
+    fn listen(&mut self, edge: Edge) {
+        let exti = unsafe { &*EXTI::ptr() };
+        let (rising, falling) = match edge {
+            Edge::Rising => (true, false),
+            Edge::Falling => (false, true),
+            Edge::RisingFalling => (true, true),
+        };
+        exti.rtsr().modify(|r, w| unsafe {
+            w.bits(if rising {
+                r.bits() | Self::MASK
+            } else {
+                r.bits() & !Self::MASK
+            })
+        });
+        exti.ftsr().modify(|r, w| unsafe {
+            w.bits(if falling {
+                r.bits() | Self::MASK
+            } else {
+                r.bits() & !Self::MASK
+            })
+        });
+        self.pin.clear_interrupt_pending_bit();
+        TRIGGERED.fetch_and(!Self::MASK, Ordering::Relaxed);
+        exti.imr()
+            .modify(|r, w| unsafe { w.bits(r.bits() | Self::MASK) });
+    }
+
+    fn unlisten(&mut self) {
+        let exti = unsafe { &*EXTI::ptr() };
+        exti.imr()
+            .modify(|r, w| unsafe { w.bits(r.bits() & !Self::MASK) });
+    }
+
+    /// Waits until `edge` is detected or `done` returns `true`
+    async fn wait_for(&mut self, edge: Edge, done: impl Fn(&Pin<P, N, Input>) -> bool) {
+        self.listen(edge);
+        poll_fn(|cx| {
+            WAKERS[N as usize].register(cx.waker());
+            if TRIGGERED.fetch_and(!Self::MASK, Ordering::Acquire) & Self::MASK != 0
+                || done(&self.pin)
+            {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+        self.unlisten();
+    }
+
+    /// Waits until the pin is high, returns immediately if it already is
+    pub async fn wait_for_high(&mut self) {
+        if self.pin.is_low() {
+            // The level is checked again after the line is unmasked,
+            // an edge before that would be missed
+            self.wait_for(Edge::Rising, |pin| pin.is_high()).await;
+        }
+    }
+
+    /// Waits until the pin is low, returns immediately if it already is
+    pub async fn wait_for_low(&mut self) {
+        if self.pin.is_high() {
+            self.wait_for(Edge::Falling, |pin| pin.is_low()).await;
+        }
+    }
+
+    /// Waits for a rising edge
+    pub async fn wait_for_rising_edge(&mut self) {
+        self.wait_for(Edge::Rising, |_| false).await;
+    }
+
+    /// Waits for a falling edge
+    pub async fn wait_for_falling_edge(&mut self) {
+        self.wait_for(Edge::Falling, |_| false).await;
+    }
+
+    /// Waits for a rising or a falling edge
+    pub async fn wait_for_any_edge(&mut self) {
+        self.wait_for(Edge::RisingFalling, |_| false).await;
+    }
+}
+
+impl<const P: char, const N: u8> embedded_hal::digital::ErrorType for ExtiInput<P, N> {
+    type Error = Infallible;
+}
+
+impl<const P: char, const N: u8> embedded_hal::digital::InputPin for ExtiInput<P, N> {
+    #[inline(always)]
+    fn is_high(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.pin.is_high())
+    }
+
+    #[inline(always)]
+    fn is_low(&mut self) -> Result<bool, Self::Error> {
+        Ok(self.pin.is_low())
+    }
+}
+
+impl<const P: char, const N: u8> embedded_hal_async::digital::Wait for ExtiInput<P, N> {
+    #[inline(always)]
+    async fn wait_for_high(&mut self) -> Result<(), Self::Error> {
+        ExtiInput::wait_for_high(self).await;
+        Ok(())
+    }
+
+    #[inline(always)]
+    async fn wait_for_low(&mut self) -> Result<(), Self::Error> {
+        ExtiInput::wait_for_low(self).await;
+        Ok(())
+    }
+
+    #[inline(always)]
+    async fn wait_for_rising_edge(&mut self) -> Result<(), Self::Error> {
+        ExtiInput::wait_for_rising_edge(self).await;
+        Ok(())
+    }
+
+    #[inline(always)]
+    async fn wait_for_falling_edge(&mut self) -> Result<(), Self::Error> {
+        ExtiInput::wait_for_falling_edge(self).await;
+        Ok(())
+    }
+
+    #[inline(always)]
+    async fn wait_for_any_edge(&mut self) -> Result<(), Self::Error> {
+        ExtiInput::wait_for_any_edge(self).await;
+        Ok(())
+    }
+}
@@ -8,6 +8,8 @@ use fugit::{HertzU32 as Hertz, RateExtU32};
 mod common;
 mod hal_02;
 mod hal_1;
+#[cfg(feature = "async")]
+mod hal_async;
 
 pub use common::{Address, Error, NoAcknowledgeSource};
 use common::{Hal02Operation, Hal1Operation};
@@ -74,6 +76,10 @@ pub struct I2c<I2C: Instance> {
 pub trait Instance:
     crate::rcc::Instance + crate::Ptr<RB = i2c1::RegisterBlock> + gpio::alt::I2cCommon
 {
+    /// Waker of the task awaiting this I2C.
+    #[cfg(feature = "async")]
+    #[doc(hidden)]
+    fn waker() -> &'static atomic_waker::AtomicWaker;
 }
 
 // Implemented by all I2C instances
@@ -81,7 +87,13 @@ macro_rules! i2c {
     ($I2C:ty: $I2c:ident) => {
         pub type $I2c = I2c<$I2C>;
 
-        impl Instance for $I2C {}
+        impl Instance for $I2C {
+            #[cfg(feature = "async")]
+            fn waker() -> &'static atomic_waker::AtomicWaker {
+                static WAKER: atomic_waker::AtomicWaker = atomic_waker::AtomicWaker::new();
+                &WAKER
+            }
+        }
     };
 }
 
@@ -12,6 +12,9 @@ use crate::ReadFlags;
 
 use nb;
 
+#[cfg(feature = "async")]
+mod hal_async;
+
 #[non_exhaustive]
 pub enum Error {
     I2CError(super::Error),
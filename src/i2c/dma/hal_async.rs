@@ -0,0 +1,265 @@
+//! Async I2C master transferring the data with DMA
+//!
+//! Besides [`I2c::on_interrupt`] in the `I2Cx_EV` and `I2Cx_ER` interrupt handlers,
+//! [`StreamX::on_interrupt`](crate::dma::StreamX::on_interrupt) must be called
+//! from the interrupt handlers of both DMA streams.
+
+use core::{future::poll_fn, mem::transmute, task::Poll};
+
+use super::{
+    Channel, ChannelX, DMASet, DMATransfer, I2CMasterDma, I2c, Instance, MemoryToPeripheral,
+    PeripheralToMemory, Rx, RxDMA, Stream, Transfer, Tx, TxDMA,
+};
+use crate::dma::traits::{Direction, PeriAddress};
+use crate::i2c::{Address, Error};
+use crate::OnDrop;
+use embedded_hal::i2c::{Operation, SevenBitAddress};
+
+impl<I2C: Instance> I2c<I2C> {
+    /// Waits until the DMA transfer completes or an I2C error occurs
+    async fn wait_dma<STREAM, const CH: u8, PERIPHERAL, DIR, BUF>(
+        &self,
+        transfer: &mut Transfer<STREAM, CH, PERIPHERAL, DIR, BUF>,
+    ) -> Result<(), Error>
+    where
+        STREAM: Stream,
+        ChannelX<CH>: Channel,
+        DIR: Direction,
+        PERIPHERAL: PeriAddress + DMASet<STREAM, CH, DIR>,
+    {
+        poll_fn(|cx| {
+            I2C::waker().register(cx.waker());
+            if let Err(e) = self.check_and_clear_error_flags() {
+                return Poll::Ready(Err(e.nack_data()));
+            }
+            match transfer.poll_complete(cx) {
+                // A DMA transfer error is an error of the bus matrix
+                Poll::Ready(result) => Poll::Ready(result.map_err(|_| Error::Bus)),
+                Poll::Pending => {
+                    self.i2c.cr2().modify(|_, w| w.iterren().set_bit());
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+}
+
+impl<I2C, TX_STREAM, const TX_CH: u8, RX_STREAM, const RX_CH: u8>
+    I2CMasterDma<I2C, TxDMA<I2C, TX_STREAM, TX_CH>, RxDMA<I2C, RX_STREAM, RX_CH>>
+where
+    I2C: Instance,
+    TX_STREAM: Stream,
+    ChannelX<TX_CH>: Channel,
+    Tx<I2C>: DMASet<TX_STREAM, TX_CH, MemoryToPeripheral>,
+
+    RX_STREAM: Stream,
+    ChannelX<RX_CH>: Channel,
+    Rx<I2C>: DMASet<RX_STREAM, RX_CH, PeripheralToMemory>,
+{
+    async fn write_dma_async(
+        hal_i2c: &I2c<I2C>,
+        tx: &mut TxDMA<I2C, TX_STREAM, TX_CH>,
+        bytes: &[u8],
+        group_start: bool,
+        last: bool,
+    ) -> Result<(), Error> {
+        let i2c = &hal_i2c.i2c;
+
+        if bytes.is_empty() {
+            if group_start {
+                // Clear condition by reading SR2
+                i2c.sr2().read();
+            }
+        } else {
+            // NOTE(unsafe) the guard destroys the transfer, which stops the stream,
+            // before `bytes` goes out of scope, also if this future is dropped
+            tx.create_transfer(unsafe { transmute::<&[u8], &'static [u8]>(bytes) });
+            // Destroys the transfer when dropped, which stops the stream
+            let mut tx = OnDrop::new(tx, |tx| {
+                if tx.created() {
+                    tx.destroy_transfer()
+                }
+            });
+            let transfer = tx.tx_transfer.as_mut().unwrap();
+
+            i2c.cr2().modify(|_, w| w.dmaen().enabled());
+            transfer.start(|_| {});
+            if group_start {
+                // Clear condition by reading SR2
+                i2c.sr2().read();
+            }
+            let result = hal_i2c.wait_dma(transfer).await;
+            i2c.cr2().modify(|_, w| w.dmaen().disabled());
+            drop(tx);
+            result?;
+
+            // Wait until the last byte is transferred
+            hal_i2c
+                .wait_for_flags(false, |sr1| sr1.btf().bit_is_set())
+                .await
+                .map_err(Error::nack_data)?;
+        }
+
+        if last {
+            i2c.cr1().modify(|_, w| w.stop().set_bit());
+        }
+
+        Ok(())
+    }
+
+    async fn read_dma_async(
+        hal_i2c: &I2c<I2C>,
+        rx: &mut RxDMA<I2C, RX_STREAM, RX_CH>,
+        buffer: &mut [u8],
+        group_start: bool,
+        group_end: bool,
+        last: bool,
+    ) -> Result<(), Error> {
+        let i2c = &hal_i2c.i2c;
+
+        if group_end {
+            // See `prepare_read`: with two or more bytes the I2C sends the NACK after
+            // the last byte requested by DMA, a single byte has to be NACKed before ADDR is cleared
+            if buffer.len() >= 2 {
+                i2c.cr2().modify(|_, w| w.last().set_bit());
+            } else {
+                i2c.cr1().modify(|_, w| w.ack().clear_bit());
+            }
+        }
+
+        // NOTE(unsafe) the guard destroys the transfer, which stops the stream,
+        // before `buffer` goes out of scope, also if this future is dropped
+        rx.create_transfer(unsafe { transmute::<&mut [u8], &'static mut [u8]>(buffer) });
+        let mut rx = OnDrop::new(rx, |rx| {
+            if rx.created() {
+                rx.destroy_transfer()
+            }
+        });
+        let transfer = rx.rx_transfer.as_mut().unwrap();
+
+        i2c.cr2().modify(|_, w| w.dmaen().enabled());
+        transfer.start(|_| {});
+        if group_start {
+            // Clear condition by reading SR2
+            i2c.sr2().read();
+        }
+        let result = hal_i2c.wait_dma(transfer).await;
+        i2c.cr2()
+            .modify(|_, w| w.dmaen().disabled().last().clear_bit());
+        drop(rx);
+        result?;
+
+        if group_end && last {
+            i2c.cr1().modify(|_, w| w.stop().set_bit());
+        }
+
+        Ok(())
+    }
+
+    async fn transaction_dma_async(
+        &mut self,
+        addr: Address,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Error> {
+        if operations
+            .iter()
+            .any(|op| matches!(op, Operation::Read(buffer) if buffer.is_empty()))
+        {
+            return Err(Error::Overrun);
+        }
+
+        let Self {
+            hal_i2c, tx, rx, ..
+        } = self;
+        let hal_i2c = &*hal_i2c;
+
+        // Release the bus if the transaction is cancelled
+        let guard = OnDrop::new((), |_| {
+            hal_i2c.i2c.cr2().modify(|_, w| {
+                w.dmaen().disabled();
+                w.last().clear_bit();
+                w.itevten().clear_bit();
+                w.itbufen().clear_bit();
+                w.iterren().clear_bit()
+            });
+            hal_i2c.i2c.cr1().modify(|_, w| w.stop().set_bit());
+        });
+
+        let count = operations.len();
+        let mut prev_read = None;
+        let mut result = Ok(());
+        for i in 0..count {
+            let read = matches!(operations[i], Operation::Read(_));
+            // Adjacent operations of the same type are merged, no repeated START between them
+            let group_start = prev_read != Some(read);
+            let last = i + 1 == count;
+            let group_end = last || matches!(operations[i + 1], Operation::Read(_)) != read;
+
+            if group_start {
+                result = hal_i2c.start_async(addr, read, prev_read.is_none()).await;
+                if result.is_err() {
+                    break;
+                }
+            }
+
+            result = match &mut operations[i] {
+                Operation::Read(buffer) => {
+                    Self::read_dma_async(hal_i2c, rx, buffer, group_start, group_end, last).await
+                }
+                Operation::Write(bytes) => {
+                    Self::write_dma_async(hal_i2c, tx, bytes, group_start, last).await
+                }
+            };
+            if result.is_err() {
+                break;
+            }
+
+            prev_read = Some(read);
+        }
+        guard.defuse();
+
+        hal_i2c.i2c.cr2().modify(|_, w| {
+            w.itevten().clear_bit();
+            w.itbufen().clear_bit();
+            w.iterren().clear_bit()
+        });
+        match result {
+            // Release the bus after a NACK, arbitration loss already switched to slave mode
+            // and leaves the bus to the other master
+            Err(Error::NoAcknowledge(_)) => {
+                hal_i2c.i2c.cr1().modify(|_, w| w.stop().set_bit());
+            }
+            Ok(()) => {
+                // Wait for the STOP to be sent
+                while hal_i2c.i2c.cr1().read().stop().bit_is_set() {}
+            }
+            _ => {}
+        }
+
+        result
+    }
+}
+
+impl<I2C, TX_STREAM, const TX_CH: u8, RX_STREAM, const RX_CH: u8>
+    embedded_hal_async::i2c::I2c<SevenBitAddress>
+    for I2CMasterDma<I2C, TxDMA<I2C, TX_STREAM, TX_CH>, RxDMA<I2C, RX_STREAM, RX_CH>>
+where
+    I2C: Instance,
+    TX_STREAM: Stream,
+    ChannelX<TX_CH>: Channel,
+    Tx<I2C>: DMASet<TX_STREAM, TX_CH, MemoryToPeripheral>,
+
+    RX_STREAM: Stream,
+    ChannelX<RX_CH>: Channel,
+    Rx<I2C>: DMASet<RX_STREAM, RX_CH, PeripheralToMemory>,
+{
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.transaction_dma_async(Address::Seven(address), operations)
+            .await
+    }
+}
@@ -0,0 +1,282 @@
+//! Async I2C master driven by the event and error interrupts
+//!
+//! [`I2c::on_interrupt`] must be called from both the `I2Cx_EV` and `I2Cx_ER` interrupt handlers:
+//!
+//! ```
+//! #[interrupt]
+//! fn I2C1_EV() {
+//!     I2c1::on_interrupt();
+//! }
+//!
+//! #[interrupt]
+//! fn I2C1_ER() {
+//!     I2c1::on_interrupt();
+//! }
+//! ```
+
+use super::{Address, Error, I2c, Instance};
+use crate::pac::i2c1;
+use crate::OnDrop;
+use core::{future::poll_fn, task::Poll};
+use embedded_hal::i2c::{Operation, SevenBitAddress, TenBitAddress};
+
+fn disable_interrupts(i2c: &i2c1::RegisterBlock) {
+    i2c.cr2().modify(|_, w| {
+        w.itevten().clear_bit();
+        w.itbufen().clear_bit();
+        w.iterren().clear_bit()
+    });
+}
+
+impl<I2C: Instance> I2c<I2C> {
+    /// Handles the `I2Cx_EV` and `I2Cx_ER` interrupts for async transactions.
+    ///
+    /// Disables the I2C interrupts and wakes the task awaiting the I2C,
+    /// which checks the status flags and enables the interrupts again if it has to wait.
+    pub fn on_interrupt() {
+        disable_interrupts(unsafe { &*I2C::ptr() });
+        I2C::waker().wake();
+    }
+
+    /// Waits until `f` returns `true` for the status register or an error flag is set.
+    ///
+    /// With `buffer` the TxE and RxNE flags also raise the event interrupt.
+    pub(super) async fn wait_for_flags(
+        &self,
+        buffer: bool,
+        f: impl Fn(&i2c1::sr1::R) -> bool,
+    ) -> Result<i2c1::sr1::R, Error> {
+        poll_fn(|cx| {
+            I2C::waker().register(cx.waker());
+            match self.check_and_clear_error_flags() {
+                Err(e) => Poll::Ready(Err(e)),
+                Ok(sr1) if f(&sr1) => Poll::Ready(Ok(sr1)),
+                Ok(_) => {
+                    self.i2c.cr2().modify(|_, w| {
+                        w.itevten().set_bit();
+                        w.iterren().set_bit();
+                        w.itbufen().bit(buffer)
+                    });
+                    Poll::Pending
+                }
+            }
+        })
+        .await
+    }
+
+    async fn send_start_async(&self, read: bool) -> Result<(), Error> {
+        // Make sure the ack and start bit is set together in a single
+        // read-modify-write operation to avoid race condition.
+        self.i2c.cr1().modify(|_, w| {
+            if read {
+                w.ack().set_bit();
+            }
+            w.start().set_bit()
+        });
+
+        // Wait until START condition was generated
+        self.wait_for_flags(false, |sr1| sr1.sb().bit_is_set())
+            .await?;
+        Ok(())
+    }
+
+    /// Sends (repeated) START and Address. ADDR is left set, so that the caller
+    /// can prepare the data phase before clearing it by reading SR2.
+    pub(super) async fn start_async(
+        &self,
+        addr: Address,
+        read: bool,
+        first_transaction: bool,
+    ) -> Result<(), Error> {
+        // Wait until a previous STOP condition finishes
+        while self.i2c.cr1().read().stop().bit_is_set() {}
+
+        // Clear all pending error bits
+        self.i2c.sr1().write(|w| unsafe { w.bits(0) });
+        self.send_start_async(read).await?;
+
+        // Set up current address, we're trying to talk to
+        let dr = self.i2c.dr();
+        match addr {
+            Address::Seven(addr) => {
+                dr.write(|w| unsafe { w.bits((u16::from(addr) << 1) | u16::from(read)) });
+            }
+            Address::Ten(addr) => {
+                let [msbs, lsbs] = addr.to_be_bytes();
+                let header = 0b11110000 | (u16::from(msbs & 0b11) << 1);
+                if !read || first_transaction {
+                    dr.write(|w| unsafe { w.bits(header) });
+                    self.wait_for_flags(false, |sr1| sr1.add10().bit_is_set())
+                        .await
+                        .map_err(Error::nack_addr)?;
+                    dr.write(|w| unsafe { w.bits(u16::from(lsbs)) });
+                    if read {
+                        // Complete the addressing in write mode,
+                        // then repeat START with the header in read mode
+                        self.wait_for_flags(false, |sr1| sr1.addr().bit_is_set())
+                            .await
+                            .map_err(Error::nack_addr)?;
+                        self.i2c.sr2().read();
+                        self.send_start_async(true).await?;
+                        dr.write(|w| unsafe { w.bits(header | 1) });
+                    }
+                } else {
+                    dr.write(|w| unsafe { w.bits(header | 1) });
+                }
+            }
+        }
+
+        // Wait until address was sent. If a NACK occurs, the ADDR bit will never be set.
+        self.wait_for_flags(false, |sr1| sr1.addr().bit_is_set())
+            .await
+            .map_err(Error::nack_addr)?;
+
+        Ok(())
+    }
+
+    async fn write_async(&self, bytes: &[u8], group_start: bool, last: bool) -> Result<(), Error> {
+        if group_start {
+            // Clear condition by reading SR2
+            self.i2c.sr2().read();
+        }
+
+        for &byte in bytes {
+            self.wait_for_flags(true, |sr1| sr1.tx_e().bit_is_set())
+                .await
+                .map_err(Error::nack_data)?;
+            self.i2c.dr().write(|w| unsafe { w.bits(u16::from(byte)) });
+        }
+
+        if !bytes.is_empty() {
+            // Wait until the last byte is transferred
+            self.wait_for_flags(false, |sr1| sr1.btf().bit_is_set())
+                .await
+                .map_err(Error::nack_data)?;
+        }
+
+        if last {
+            self.i2c.cr1().modify(|_, w| w.stop().set_bit());
+        }
+
+        Ok(())
+    }
+
+    async fn read_async(
+        &self,
+        buffer: &mut [u8],
+        group_start: bool,
+        group_end: bool,
+        last: bool,
+    ) -> Result<(), Error> {
+        let len = buffer.len();
+        for (i, byte) in buffer.iter_mut().enumerate() {
+            let nack = group_end && i + 1 == len;
+            if nack {
+                // Prepare to send NACK after the next byte. For a single byte
+                // this has to happen before ADDR is cleared.
+                self.i2c.cr1().modify(|_, w| w.ack().clear_bit());
+            }
+            if group_start && i == 0 {
+                // Clear condition by reading SR2
+                self.i2c.sr2().read();
+            }
+            if nack && last {
+                self.i2c.cr1().modify(|_, w| w.stop().set_bit());
+            }
+
+            self.wait_for_flags(true, |sr1| sr1.rx_ne().bit_is_set())
+                .await
+                .map_err(Error::nack_data)?;
+            *byte = self.i2c.dr().read().bits() as u8;
+        }
+
+        Ok(())
+    }
+
+    async fn transaction_ops(
+        &self,
+        addr: Address,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Error> {
+        let count = operations.len();
+        let mut prev_read = None;
+        for i in 0..count {
+            let read = matches!(operations[i], Operation::Read(_));
+            // Adjacent operations of the same type are merged, no repeated START between them
+            let group_start = prev_read != Some(read);
+            let last = i + 1 == count;
+            let group_end = last || matches!(operations[i + 1], Operation::Read(_)) != read;
+
+            if group_start {
+                self.start_async(addr, read, prev_read.is_none()).await?;
+            }
+
+            match &mut operations[i] {
+                Operation::Read(buffer) => {
+                    self.read_async(buffer, group_start, group_end, last)
+                        .await?
+                }
+                Operation::Write(bytes) => self.write_async(bytes, group_start, last).await?,
+            }
+
+            prev_read = Some(read);
+        }
+
+        // Wait for the STOP to be sent
+        while self.i2c.cr1().read().stop().bit_is_set() {}
+
+        Ok(())
+    }
+
+    async fn transaction_async(
+        &mut self,
+        addr: Address,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Error> {
+        if operations
+            .iter()
+            .any(|op| matches!(op, Operation::Read(buffer) if buffer.is_empty()))
+        {
+            return Err(Error::Overrun);
+        }
+
+        // Release the bus if the transaction is cancelled
+        let guard = OnDrop::new((), |_| {
+            disable_interrupts(&self.i2c);
+            self.i2c.cr1().modify(|_, w| w.stop().set_bit());
+        });
+        let result = self.transaction_ops(addr, operations).await;
+        guard.defuse();
+
+        disable_interrupts(&self.i2c);
+        // Release the bus after a NACK, arbitration loss already switched to slave mode
+        // and leaves the bus to the other master
+        if let Err(Error::NoAcknowledge(_)) = result {
+            self.i2c.cr1().modify(|_, w| w.stop().set_bit());
+        }
+
+        result
+    }
+}
+
+impl<I2C: Instance> embedded_hal_async::i2c::I2c<SevenBitAddress> for I2c<I2C> {
+    async fn transaction(
+        &mut self,
+        address: u8,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.transaction_async(Address::Seven(address), operations)
+            .await
+    }
+}
+
+impl<I2C: Instance> embedded_hal_async::i2c::I2c<TenBitAddress> for I2c<I2C> {
+    async fn transaction(
+        &mut self,
+        address: u16,
+        operations: &mut [Operation<'_>],
+    ) -> Result<(), Self::Error> {
+        self.transaction_async(Address::Ten(address), operations)
+            .await
+    }
+}
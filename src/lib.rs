@@ -119,6 +119,8 @@ cfg_select! {
 pub mod rng;
 
 pub mod dma;
+#[cfg(feature = "dma2d")]
+pub mod dma2d;
 #[cfg(feature = "dsihost")]
 pub mod dsi;
 pub mod dwt;
@@ -131,6 +133,7 @@ pub mod fsmc_lcd;
 #[cfg(all(feature = "dma2d", feature = "ltdc"))]
 pub mod ltdc;
 pub mod prelude;
+pub mod pwr;
 pub mod qei;
 #[cfg(feature = "quadspi")]
 pub mod qspi;
@@ -153,6 +156,11 @@ mod sealed {
 }
 pub(crate) use sealed::Sealed;
 
+#[cfg(feature = "async")]
+mod on_drop;
+#[cfg(feature = "async")]
+pub(crate) use on_drop::OnDrop;
+
 fn stripped_type_name<T>() -> &'static str {
     let s = core::any::type_name::<T>();
     let p = s.split("::");
@@ -233,6 +241,10 @@ pub trait Listen {
 }
 
 impl<RB, const A: usize> Sealed for Periph<RB, A> {}
+impl Sealed for u8 {}
+impl Sealed for u16 {}
+impl Sealed for u32 {}
+impl Sealed for [u8; 3] {}
 
 pub trait Ptr: Sealed {
     /// RegisterBlock structure
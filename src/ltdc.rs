@@ -1,17 +1,86 @@
 //! Interface to the LCD-TFT display controller
 //!
 //! <div class="warning">Not tested yet</div>
+//!
+//! A layer is double buffered with `set_back_buffer`: the drawing functions write
+//! the back buffer, which `swap_buffers` shows on the next vertical blanking.
+//! `wait_for_vsync_async` and `swap_buffers_async` need `on_interrupt` in the
+//! `LTDC` interrupt handler:
+//!
+//! ```
+//! #[interrupt]
+//! fn LTDC() {
+//!     ltdc::on_interrupt();
+//! }
+//! ```
 
 #[cfg_attr(test, allow(unused_imports))]
 use micromath::F32Ext;
 
+#[cfg(feature = "embedded-graphics")]
+mod graphics;
+#[cfg(feature = "embedded-graphics")]
+pub use graphics::{DrawWord, LayerTarget};
+
+#[cfg(feature = "async")]
+use crate::Listen;
 use crate::{
+    dma2d::{Dma2d, FrameBuffer},
     gpio::{alt::ltdc as alt, PinSpeed, Speed},
     pac::{DMA2D, LTDC, RCC},
     rcc::{Enable, Reset},
+    ClearFlags, ReadFlags,
 };
+use enumflags2::BitFlags;
 use fugit::HertzU32 as Hertz;
 
+#[cfg(feature = "async")]
+static WAKER: atomic_waker::AtomicWaker = atomic_waker::AtomicWaker::new();
+
+/// Handles the `LTDC` interrupt for `wait_for_vsync_async` and `swap_buffers_async`.
+///
+/// Disables the register reload interrupt and wakes the task awaiting it,
+/// the other interrupts are left untouched.
+#[cfg(feature = "async")]
+pub fn on_interrupt() {
+    let ltdc = unsafe { &*LTDC::ptr() };
+    ltdc.ier()
+        .modify(|r, w| unsafe { w.bits(r.bits() & !(Event::RegisterReload as u32)) });
+    WAKER.wake();
+}
+
+/// LTDC interrupt events
+#[enumflags2::bitflags]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[repr(u32)]
+pub enum Event {
+    /// Line interrupt enable, the line is set with `set_line_interrupt_position`
+    Line = 1 << 0,
+    /// FIFO underrun interrupt enable
+    FifoUnderrun = 1 << 1,
+    /// Transfer error interrupt enable
+    TransferError = 1 << 2,
+    /// Register reload interrupt enable
+    RegisterReload = 1 << 3,
+}
+
+/// LTDC status flags
+#[enumflags2::bitflags]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[repr(u32)]
+pub enum Flag {
+    /// Line reached
+    Line = 1 << 0,
+    /// FIFO underrun
+    FifoUnderrun = 1 << 1,
+    /// Transfer error
+    TransferError = 1 << 2,
+    /// Shadow registers reloaded
+    RegisterReload = 1 << 3,
+}
+
 /// Display configuration constants
 pub struct DisplayConfig {
     pub active_width: u16,
@@ -42,6 +111,17 @@ pub enum Layer {
     L2 = 1,
 }
 
+/// Layer blending factors
+///
+/// The blended color is `BF1 * layer color + BF2 * color below`, with:
+/// * `Constant`: `BF1 = constant alpha`, `BF2 = 1 - constant alpha`
+/// * `PixelTimesConstant`: `BF1 = pixel alpha * constant alpha`, `BF2 = 1 - BF1`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlendingFactor {
+    Constant,
+    PixelTimesConstant,
+}
+
 #[allow(unused)]
 pub struct RedPins {
     r0: alt::R0,
@@ -194,13 +274,17 @@ pub struct DisplayController<T: 'static + SupportedWord> {
     /// ltdc instance
     _ltdc: LTDC,
     /// dma2d instance
-    _dma2d: DMA2D,
+    dma2d: Dma2d,
     /// Configuration structure
     config: DisplayConfig,
     /// layer 1 buffer
     buffer1: Option<&'static mut [T]>,
     /// layer 2 buffer
     buffer2: Option<&'static mut [T]>,
+    /// layer 1 back buffer
+    back_buffer1: Option<&'static mut [T]>,
+    /// layer 2 back buffer
+    back_buffer2: Option<&'static mut [T]>,
     /// Pixels format in the layers
     pixel_format: PixelFormat,
 }
@@ -344,10 +428,12 @@ impl<T: 'static + SupportedWord> DisplayController<T> {
 
         DisplayController {
             _ltdc: ltdc,
-            _dma2d: dma2d,
+            dma2d: Dma2d { periph: dma2d },
             config,
             buffer1: None,
             buffer2: None,
+            back_buffer1: None,
+            back_buffer2: None,
             pixel_format,
         }
     }
@@ -355,9 +441,10 @@ impl<T: 'static + SupportedWord> DisplayController<T> {
     /// Configure the layer
     ///
     /// Note : the choice is made (for the sake of simplicity) to make the layer
-    /// as big as the screen
+    /// as big as the screen, use `set_layer_window` to show a part of it
     ///
-    /// Color Keying and CLUT are not yet supported
+    /// The layer is opaque, without color keying and CLUT, see `set_constant_alpha`,
+    /// `set_blending_factor`, `set_color_key` and `load_clut`
     pub fn config_layer(
         &mut self,
         layer: Layer,
@@ -388,7 +475,7 @@ impl<T: 'static + SupportedWord> DisplayController<T> {
         _layer.pfcr().write(|w| {
             w.pf().set(match &pixel_format {
                 PixelFormat::ARGB8888 => 0b000,
-                // PixelFormat::RGB888 => 0b001,
+                PixelFormat::RGB888 => 0b001,
                 PixelFormat::RGB565 => 0b010,
                 PixelFormat::ARGB1555 => 0b011,
                 PixelFormat::ARGB4444 => 0b100,
@@ -421,7 +508,7 @@ impl<T: 'static + SupportedWord> DisplayController<T> {
         // Color frame buffer line length (active*byte per pixel + 3), and pitch
         let byte_per_pixel: u16 = match &pixel_format {
             PixelFormat::ARGB8888 => 4,
-            // PixelFormat::RGB888 => 24, unsupported for now because u24 does not exist
+            PixelFormat::RGB888 => 3,
             PixelFormat::RGB565 => 2,
             PixelFormat::ARGB1555 => 2,
             PixelFormat::ARGB4444 => 16,
@@ -444,10 +531,10 @@ impl<T: 'static + SupportedWord> DisplayController<T> {
         _layer.cr().modify(|_, w| w.cluten().clear_bit());
 
         // Config DMA2D hardware acceleration : pixel format, no CLUT
-        self._dma2d.fgpfccr().write(|w| unsafe {
+        self.dma2d.periph.fgpfccr().write(|w| unsafe {
             w.bits(match &pixel_format {
                 PixelFormat::ARGB8888 => 0b000,
-                // PixelFormat::RGB888 => 0b0001, unsupported for now because u24 does not exist
+                PixelFormat::RGB888 => 0b0001,
                 PixelFormat::RGB565 => 0b0010,
                 PixelFormat::ARGB1555 => 0b0011,
                 PixelFormat::ARGB4444 => 0b0100,
@@ -475,16 +562,120 @@ impl<T: 'static + SupportedWord> DisplayController<T> {
             .modify(|_, w| w.len().set_bit());
     }
 
-    /// Draw a pixel at position (x,y) on the given layer
+    /// Disable the layer, it shows the default color
+    pub fn disable_layer(&self, layer: Layer) {
+        self._ltdc
+            .layer(layer as usize)
+            .cr()
+            .modify(|_, w| w.len().clear_bit());
+    }
+
+    /// Move the layer window to (x,y) on the screen and resize it
+    ///
+    /// The window shows the top left part of the layer buffer, the change is applied
+    /// on the next reload.
+    pub fn set_layer_window(&mut self, layer: Layer, x: u16, y: u16, width: u16, height: u16) {
+        if width == 0
+            || height == 0
+            || x + width > self.config.active_width
+            || y + height > self.config.active_height
+        {
+            panic!("Invalid layer window");
+        }
+        let _layer = self._ltdc.layer(layer as usize);
+
+        let h_win_start = self.config.h_sync + self.config.h_back_porch - 1 + x;
+        let v_win_start = self.config.v_sync + self.config.v_back_porch - 1 + y;
+        _layer.whpcr().write(|w| {
+            w.whstpos().set(h_win_start + 1);
+            w.whsppos().set(h_win_start + width)
+        });
+        _layer.wvpcr().write(|w| {
+            w.wvstpos().set(v_win_start + 1);
+            w.wvsppos().set(v_win_start + height)
+        });
+
+        // The pitch of the buffer is kept, only the line length changes
+        let pitch = _layer.cfblr().read().cfbp().bits();
+        let byte_per_pixel = pitch / self.config.active_width;
+        _layer
+            .cfblr()
+            .modify(|_, w| w.cfbll().set(width * byte_per_pixel + 3));
+        _layer.cfblnr().write(|w| w.cfblnbr().set(height));
+    }
+
+    /// Set the color (ARGB8888) shown outside the layer window or when the layer is disabled
+    pub fn set_default_color(&mut self, layer: Layer, color: u32) {
+        self._ltdc
+            .layer(layer as usize)
+            .dccr()
+            .write(|w| unsafe { w.bits(color) });
+    }
+
+    /// Set the constant alpha of the layer, used by the blending factors
+    pub fn set_constant_alpha(&mut self, layer: Layer, alpha: u8) {
+        self._ltdc
+            .layer(layer as usize)
+            .cacr()
+            .write(|w| w.consta().set(alpha));
+    }
+
+    /// Select how the layer is blended with the layer below it (or the background)
+    pub fn set_blending_factor(&mut self, layer: Layer, factor: BlendingFactor) {
+        let (bf1, bf2) = match factor {
+            BlendingFactor::Constant => (0b100, 0b101),
+            BlendingFactor::PixelTimesConstant => (0b110, 0b111),
+        };
+        self._ltdc
+            .layer(layer as usize)
+            .bfcr()
+            .write(|w| unsafe { w.bits(bf1 << 8 | bf2) });
+    }
+
+    /// Enable color keying: the pixels of the layer with the RGB888 `key` color are transparent.
+    /// `None` disables color keying.
+    pub fn set_color_key(&mut self, layer: Layer, key: Option<u32>) {
+        let _layer = self._ltdc.layer(layer as usize);
+        if let Some(key) = key {
+            _layer.ckcr().write(|w| unsafe { w.bits(key & 0xFF_FFFF) });
+        }
+        _layer.cr().modify(|_, w| w.colken().bit(key.is_some()));
+    }
+
+    /// Load the RGB888 colors of the Color Lookup table (CLUT) and enable it.
+    ///
+    /// The CLUT maps the luminance of the `L8`, `AL44` and `AL88` formats to colors. It should
+    /// be loaded while the layer is disabled or during the vertical blanking.
+    pub fn load_clut(&mut self, layer: Layer, clut: &[u32]) {
+        assert!(clut.len() <= 256);
+        let _layer = self._ltdc.layer(layer as usize);
+        for (address, color) in clut.iter().enumerate() {
+            _layer
+                .clutwr()
+                .write(|w| unsafe { w.bits((address as u32) << 24 | color & 0xFF_FFFF) });
+        }
+        _layer.cr().modify(|_, w| w.cluten().set_bit());
+    }
+
+    /// Disable the Color Lookup table (CLUT)
+    pub fn disable_clut(&mut self, layer: Layer) {
+        self._ltdc
+            .layer(layer as usize)
+            .cr()
+            .modify(|_, w| w.cluten().clear_bit());
+    }
+
+    /// Draw a pixel at position (x,y) on the given layer (on its back buffer if it has one)
     pub fn draw_pixel(&mut self, layer: Layer, x: usize, y: usize, color: T) {
         if x >= self.config.active_width as usize || y >= self.config.active_height as usize {
             panic!("Invalid (x,y) pixel position");
         }
 
         (match layer {
-            Layer::L1 => self.buffer1.as_mut().unwrap(),
-            Layer::L2 => self.buffer2.as_mut().unwrap(),
-        })[x + self.config.active_width as usize * y] = color;
+            Layer::L1 => self.back_buffer1.as_mut().or(self.buffer1.as_mut()),
+            Layer::L2 => self.back_buffer2.as_mut().or(self.buffer2.as_mut()),
+        })
+        .unwrap()[x + self.config.active_width as usize * y] = color;
     }
 
     /// Draw hardware accelerated rectangle
@@ -500,10 +691,10 @@ impl<T: 'static + SupportedWord> DisplayController<T> {
         color: u32,
     ) {
         // Output color format
-        self._dma2d.opfccr().write(|w| {
+        self.dma2d.periph.opfccr().write(|w| {
             w.cm().bits(match &self.pixel_format {
                 PixelFormat::ARGB8888 => 0b000,
-                // PixelFormat::RGB888 => 0b001, unsupported for now
+                PixelFormat::RGB888 => 0b001,
                 PixelFormat::RGB565 => 0b010,
                 PixelFormat::ARGB1555 => 0b011,
                 PixelFormat::ARGB4444 => 0b100,
@@ -512,15 +703,15 @@ impl<T: 'static + SupportedWord> DisplayController<T> {
         });
 
         // Output color
-        self._dma2d.ocolr().write_with_zero(|w| w.bits(color));
+        self.dma2d.periph.ocolr().write_with_zero(|w| w.bits(color));
 
         // Destination memory address
         let offset: isize = (top_left.0 + self.config.active_width as usize * top_left.1) as isize;
-        self._dma2d.omar().write_with_zero(|w| {
+        self.dma2d.periph.omar().write_with_zero(|w| {
             w.bits(
                 (match layer {
-                    Layer::L1 => self.buffer1.as_ref(),
-                    Layer::L2 => self.buffer2.as_ref(),
+                    Layer::L1 => self.back_buffer1.as_ref().or(self.buffer1.as_ref()),
+                    Layer::L2 => self.back_buffer2.as_ref().or(self.buffer2.as_ref()),
                 })
                 .unwrap()
                 .as_ptr()
@@ -529,39 +720,208 @@ impl<T: 'static + SupportedWord> DisplayController<T> {
         });
 
         // Pixels per line and number of lines
-        self._dma2d.nlr().write(|w| {
+        self.dma2d.periph.nlr().write(|w| {
             w.pl().bits((bottom_right.0 - top_left.0) as u16);
             w.nl().bits((bottom_right.1 - top_left.1) as u16)
         });
 
         // Line offset
-        self._dma2d.oor().write(|w| {
+        self.dma2d.periph.oor().write(|w| {
             w.lo()
                 .bits(top_left.0 as u16 + self.config.active_width - bottom_right.0 as u16)
         });
 
         // Start transfert: register to memory mode
-        self._dma2d
+        self.dma2d
+            .periph
             .cr()
             .modify(|_, w| w.mode().bits(0b11).start().set_bit());
     }
 
+    /// DMA2D and the frame buffer of the layer (its back buffer if it has one),
+    /// for hardware accelerated drawing
+    ///
+    /// # Panics
+    ///
+    /// * When the layer is not configured or its pixel format can't be written by the DMA2D.
+    pub fn dma2d(&mut self, layer: Layer) -> (&mut Dma2d, FrameBuffer<'_>) {
+        let (format, width) = (self.pixel_format.into(), self.config.active_width);
+        let (buffer, dma2d) = self.draw_buffer(layer);
+        (dma2d, FrameBuffer::new(buffer, format, width))
+    }
+
+    /// Buffer drawn into (the back buffer if the layer has one) and the DMA2D
+    fn draw_buffer(&mut self, layer: Layer) -> (&mut [T], &mut Dma2d) {
+        let buffer = match layer {
+            Layer::L1 => self
+                .back_buffer1
+                .as_deref_mut()
+                .or(self.buffer1.as_deref_mut()),
+            Layer::L2 => self
+                .back_buffer2
+                .as_deref_mut()
+                .or(self.buffer2.as_deref_mut()),
+        }
+        .unwrap();
+        (buffer, &mut self.dma2d)
+    }
+
     /// Reload display controller immediatly
     pub fn reload(&self) {
         // Reload ltdc config immediatly
         self._ltdc.srcr().modify(|_, w| w.imr().set_bit());
     }
+
+    /// Reload display controller on the next vertical blanking
+    pub fn reload_on_vblank(&self) {
+        self._ltdc.srcr().modify(|_, w| w.vbr().set_bit());
+    }
+
+    /// Set the line of the `Line` event, counted from the first line of the vertical sync
+    pub fn set_line_interrupt_position(&mut self, line: u16) {
+        self._ltdc
+            .lipcr()
+            .write(|w| unsafe { w.bits(line as u32 & 0x7FF) });
+    }
+
+    /// Reload display controller on the next vertical blanking and wait until it is done.
+    ///
+    /// Returns at the start of the vertical blanking, the pending layer changes are applied.
+    pub fn wait_for_vsync(&mut self) {
+        self.clear_flags(Flag::RegisterReload);
+        self.reload_on_vblank();
+        while !self.flags().contains(Flag::RegisterReload) {}
+        self.clear_flags(Flag::RegisterReload);
+    }
+
+    /// Reload display controller on the next vertical blanking and wait for the
+    /// register reload interrupt.
+    #[cfg(feature = "async")]
+    pub async fn wait_for_vsync_async(&mut self) {
+        self.clear_flags(Flag::RegisterReload);
+        self.reload_on_vblank();
+        core::future::poll_fn(|cx| {
+            WAKER.register(cx.waker());
+            if self.flags().contains(Flag::RegisterReload) {
+                core::task::Poll::Ready(())
+            } else {
+                self.listen(Event::RegisterReload);
+                core::task::Poll::Pending
+            }
+        })
+        .await;
+        self.unlisten(Event::RegisterReload);
+        self.clear_flags(Flag::RegisterReload);
+    }
+
+    /// Make the layer double buffered, `buffer` is drawn into while the other one is shown
+    pub fn set_back_buffer(&mut self, layer: Layer, buffer: &'static mut [T]) {
+        assert!(
+            buffer.len() == self.config.active_height as usize * self.config.active_width as usize
+        );
+        match layer {
+            Layer::L1 => self.back_buffer1 = Some(buffer),
+            Layer::L2 => self.back_buffer2 = Some(buffer),
+        }
+    }
+
+    /// Buffer drawn into of a double buffered layer
+    pub fn back_buffer(&mut self, layer: Layer) -> &mut [T] {
+        match layer {
+            Layer::L1 => self.back_buffer1.as_deref_mut(),
+            Layer::L2 => self.back_buffer2.as_deref_mut(),
+        }
+        .expect("Layer is not double buffered")
+    }
+
+    /// Show the back buffer of the layer from the next vertical blanking, the shown
+    /// buffer becomes the back buffer
+    fn present_back_buffer(&mut self, layer: Layer) {
+        let (front, back) = match layer {
+            Layer::L1 => (&mut self.buffer1, &mut self.back_buffer1),
+            Layer::L2 => (&mut self.buffer2, &mut self.back_buffer2),
+        };
+        let (front, back) = (
+            front.as_mut().unwrap(),
+            back.as_mut().expect("Layer is not double buffered"),
+        );
+        core::mem::swap(front, back);
+        self._ltdc
+            .layer(layer as usize)
+            .cfbar()
+            .write(|w| w.cfbadd().set(front.as_ptr() as u32));
+    }
+
+    /// Show the back buffer of a double buffered layer on the next vertical blanking
+    /// and wait until it is shown, the shown buffer becomes the back buffer.
+    pub fn swap_buffers(&mut self, layer: Layer) {
+        self.present_back_buffer(layer);
+        self.wait_for_vsync();
+    }
+
+    /// Show the back buffer of a double buffered layer on the next vertical blanking
+    /// and wait for the register reload interrupt, the shown buffer becomes the back buffer.
+    #[cfg(feature = "async")]
+    pub async fn swap_buffers_async(&mut self, layer: Layer) {
+        self.present_back_buffer(layer);
+        self.wait_for_vsync_async().await;
+    }
+}
+
+impl<T: 'static + SupportedWord> crate::Listen for DisplayController<T> {
+    type Event = Event;
+
+    fn listen_event(
+        &mut self,
+        disable: Option<BitFlags<Self::Event>>,
+        enable: Option<BitFlags<Self::Event>>,
+    ) {
+        self._ltdc.ier().modify(|r, w| unsafe {
+            w.bits({
+                let mut bits = r.bits();
+                if let Some(d) = disable {
+                    bits &= !d.bits();
+                }
+                if let Some(e) = enable {
+                    bits |= e.bits();
+                }
+                bits
+            })
+        });
+    }
+}
+
+impl<T: 'static + SupportedWord> crate::ClearFlags for DisplayController<T> {
+    type Flag = Flag;
+
+    #[inline(always)]
+    fn clear_flags(&mut self, flags: impl Into<BitFlags<Self::Flag>>) {
+        self._ltdc
+            .icr()
+            .write(|w| unsafe { w.bits(flags.into().bits()) });
+    }
+}
+
+impl<T: 'static + SupportedWord> crate::ReadFlags for DisplayController<T> {
+    type Flag = Flag;
+
+    #[inline(always)]
+    fn flags(&self) -> BitFlags<Self::Flag> {
+        BitFlags::from_bits_truncate(self._ltdc.isr().read().bits())
+    }
 }
 
 /// Available PixelFormats to work with
 ///
 /// Notes :
+/// * `RGB888`: 24-bit color, stored in `[u8; 3]` words
 /// * `L8`: 8-bit luminance or CLUT
 /// * `AL44`: 4-bit alpha + 4-bit luminance
 /// * `AL88`: 8-bit alpha + 8-bit luminance
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum PixelFormat {
     ARGB8888,
-    // RGB888(u24) unsupported for now because u24 does not exist
+    RGB888,
     RGB565,
     ARGB1555,
     ARGB4444,
@@ -570,7 +930,8 @@ pub enum PixelFormat {
     AL88,
 }
 
-pub trait SupportedWord {}
+pub trait SupportedWord: Copy {}
 impl SupportedWord for u8 {}
 impl SupportedWord for u16 {}
 impl SupportedWord for u32 {}
+impl SupportedWord for [u8; 3] {}
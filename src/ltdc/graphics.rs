@@ -0,0 +1,228 @@
+//! [`DrawTarget`] for the LTDC layers
+//!
+//! ```
+//! let mut layer = display.draw_target(Layer::L1);
+//! Circle::new(Point::new(20, 20), 16)
+//!     .into_styled(PrimitiveStyle::with_fill(Rgb565::RED))
+//!     .draw(&mut layer)?;
+//! ```
+//!
+//! Solid fills of the `RGB565`, `ARGB8888` and `RGB888` layers use the DMA2D.
+
+use core::convert::Infallible;
+
+use embedded_graphics_core::{
+    pixelcolor::{Gray8, IntoStorage, Rgb565, Rgb888},
+    prelude::*,
+    primitives::{PointsIter, Rectangle},
+};
+
+use super::{DisplayController, Layer, PixelFormat, SupportedWord};
+use crate::dma2d::{FrameBuffer, Rect};
+
+/// Words of the layer buffers drawn with embedded-graphics
+pub trait DrawWord: SupportedWord + crate::Sealed {
+    /// Color of the pixels
+    type Color: PixelColor;
+    /// Pixel format of the layers
+    const FORMAT: PixelFormat;
+
+    /// Converts `color` to the word stored in the buffer
+    fn from_color(color: Self::Color) -> Self;
+
+    /// Color register value of the DMA2D, `None` if the DMA2D can't write the format
+    fn dma2d_color(color: Self::Color) -> Option<u32>;
+}
+
+impl DrawWord for u16 {
+    type Color = Rgb565;
+    const FORMAT: PixelFormat = PixelFormat::RGB565;
+
+    #[inline(always)]
+    fn from_color(color: Rgb565) -> Self {
+        color.into_storage()
+    }
+
+    #[inline(always)]
+    fn dma2d_color(color: Rgb565) -> Option<u32> {
+        Some(color.into_storage() as u32)
+    }
+}
+
+impl DrawWord for u32 {
+    type Color = Rgb888;
+    const FORMAT: PixelFormat = PixelFormat::ARGB8888;
+
+    /// Opaque pixel
+    #[inline(always)]
+    fn from_color(color: Rgb888) -> Self {
+        0xFF00_0000 | color.into_storage()
+    }
+
+    #[inline(always)]
+    fn dma2d_color(color: Rgb888) -> Option<u32> {
+        Some(Self::from_color(color))
+    }
+}
+
+impl DrawWord for [u8; 3] {
+    type Color = Rgb888;
+    const FORMAT: PixelFormat = PixelFormat::RGB888;
+
+    /// Blue is stored first
+    #[inline(always)]
+    fn from_color(color: Rgb888) -> Self {
+        let [b, g, r, _] = color.into_storage().to_le_bytes();
+        [b, g, r]
+    }
+
+    #[inline(always)]
+    fn dma2d_color(color: Rgb888) -> Option<u32> {
+        Some(color.into_storage())
+    }
+}
+
+impl DrawWord for u8 {
+    type Color = Gray8;
+    const FORMAT: PixelFormat = PixelFormat::L8;
+
+    #[inline(always)]
+    fn from_color(color: Gray8) -> Self {
+        color.into_storage()
+    }
+
+    #[inline(always)]
+    fn dma2d_color(_color: Gray8) -> Option<u32> {
+        None
+    }
+}
+
+/// Layer of a [`DisplayController`] implementing [`DrawTarget`]
+///
+/// Draws into the back buffer of double buffered layers.
+pub struct LayerTarget<'a, T: 'static + DrawWord> {
+    controller: &'a mut DisplayController<T>,
+    layer: Layer,
+}
+
+impl<T: 'static + DrawWord> DisplayController<T> {
+    /// Draw target of the layer
+    ///
+    /// # Panics
+    ///
+    /// * When the layer is not configured with the pixel format of `T`.
+    pub fn draw_target(&mut self, layer: Layer) -> LayerTarget<'_, T> {
+        let pf = match T::FORMAT {
+            PixelFormat::ARGB8888 => 0b000,
+            PixelFormat::RGB888 => 0b001,
+            PixelFormat::RGB565 => 0b010,
+            _ => 0b101,
+        };
+        assert_eq!(
+            self._ltdc.layer(layer as usize).pfcr().read().pf().bits(),
+            pf
+        );
+        LayerTarget {
+            controller: self,
+            layer,
+        }
+    }
+}
+
+impl<T: 'static + DrawWord> OriginDimensions for LayerTarget<'_, T> {
+    fn size(&self) -> Size {
+        Size::new(
+            self.controller.config.active_width as u32,
+            self.controller.config.active_height as u32,
+        )
+    }
+}
+
+impl<T: 'static + DrawWord> DrawTarget for LayerTarget<'_, T> {
+    type Color = T::Color;
+    type Error = Infallible;
+
+    fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Pixel<Self::Color>>,
+    {
+        let size = self.size();
+        let width = size.width as usize;
+        let (buffer, _) = self.controller.draw_buffer(self.layer);
+        for Pixel(point, color) in pixels {
+            // Out of bounds pixels are discarded
+            if point.x >= 0
+                && point.y >= 0
+                && (point.x as u32) < size.width
+                && (point.y as u32) < size.height
+            {
+                buffer[point.x as usize + width * point.y as usize] = T::from_color(color);
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_contiguous<I>(&mut self, area: &Rectangle, colors: I) -> Result<(), Self::Error>
+    where
+        I: IntoIterator<Item = Self::Color>,
+    {
+        let clipped = area.intersection(&self.bounding_box());
+        if clipped != *area {
+            return self.draw_iter(
+                area.points()
+                    .zip(colors)
+                    .map(|(point, color)| Pixel(point, color)),
+            );
+        }
+        if area.is_zero_sized() {
+            return Ok(());
+        }
+
+        // The area is inside the buffer, it is written line by line
+        let width = self.size().width as usize;
+        let (buffer, _) = self.controller.draw_buffer(self.layer);
+        let (x, y) = (area.top_left.x as usize, area.top_left.y as usize);
+        let mut colors = colors.into_iter();
+        for line in y..y + area.size.height as usize {
+            let start = x + width * line;
+            for (word, color) in buffer[start..start + area.size.width as usize]
+                .iter_mut()
+                .zip(&mut colors)
+            {
+                *word = T::from_color(color);
+            }
+        }
+        Ok(())
+    }
+
+    fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+        let area = area.intersection(&self.bounding_box());
+        if area.is_zero_sized() {
+            return Ok(());
+        }
+
+        let width = self.size().width as usize;
+        let (buffer, dma2d) = self.controller.draw_buffer(self.layer);
+        if let Some(dma2d_color) = T::dma2d_color(color) {
+            let rect = Rect::new(
+                area.top_left.x as u16,
+                area.top_left.y as u16,
+                area.size.width as u16,
+                area.size.height as u16,
+            );
+            let mut frame_buffer = FrameBuffer::new(&mut *buffer, T::FORMAT.into(), width as u16);
+            if dma2d.fill(&mut frame_buffer, rect, dma2d_color).is_ok() {
+                return Ok(());
+            }
+        }
+
+        // Without DMA2D, or if it failed
+        let word = T::from_color(color);
+        let (x, y) = (area.top_left.x as usize, area.top_left.y as usize);
+        for line in y..y + area.size.height as usize {
+            let start = x + width * line;
+            buffer[start..start + area.size.width as usize].fill(word);
+        }
+        Ok(())
+    }
+}
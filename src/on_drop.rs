@@ -0,0 +1,46 @@
+//! Drop guard cleaning up after async operations
+
+use core::mem::ManuallyDrop;
+use core::ops::{Deref, DerefMut};
+use core::ptr;
+
+/// Calls the closure with the guarded value when dropped, used to clean up after an async
+/// operation cancelled by dropping its future
+pub(crate) struct OnDrop<T, F: FnMut(&mut T)> {
+    value: T,
+    f: F,
+}
+
+impl<T, F: FnMut(&mut T)> OnDrop<T, F> {
+    pub(crate) fn new(value: T, f: F) -> Self {
+        Self { value, f }
+    }
+
+    /// Returns the guarded value without calling the closure
+    pub(crate) fn defuse(self) -> T {
+        let this = ManuallyDrop::new(self);
+        // NOTE(unsafe) both fields are moved out exactly once and `this` is never dropped
+        let (value, _f) = unsafe { (ptr::read(&this.value), ptr::read(&this.f)) };
+        value
+    }
+}
+
+impl<T, F: FnMut(&mut T)> Deref for OnDrop<T, F> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T, F: FnMut(&mut T)> DerefMut for OnDrop<T, F> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T, F: FnMut(&mut T)> Drop for OnDrop<T, F> {
+    fn drop(&mut self) {
+        (self.f)(&mut self.value)
+    }
+}
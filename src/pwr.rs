@@ -0,0 +1,443 @@
+//! Power control: Sleep, Stop and Standby modes and their wakeup sources
+//!
+//! ```
+//! let mut pwr = dp.PWR.constrain(&mut rcc);
+//! let mut scb = cp.SCB;
+//!
+//! // Any interrupt wakes up, the clocks are restored
+//! pwr.enter_stop(&mut scb, StopConfig::default().regulator(Regulator::LowPower));
+//!
+//! pwr.enable_wakeup_pin(WakeupPin::Pin1, true);
+//! pwr.enter_standby(&mut scb);
+//! ```
+//!
+//! The programmable voltage detector (PVD) compares VDD with a threshold, e.g. to save
+//! data before a brown-out. Its output is EXTI line 16:
+//!
+//! ```
+//! pwr.enable_pvd(PvdLevel::V2_9);
+//! pwr.trigger_pvd_on_edge(&mut dp.EXTI, Edge::Rising);
+//! pwr.enable_pvd_interrupt(&mut dp.EXTI);
+//!
+//! #[interrupt]
+//! fn PVD() {
+//!     // VDD dropped below the threshold
+//! }
+//! ```
+//!
+//! The configuration of the clocks is kept during Stop mode, while the oscillators and PLLs
+//! are switched off and the system clock falls back to the HSI. [`Pwr::enter_stop`] switches
+//! them on again after wakeup, so the [`Clocks`](crate::rcc::Clocks) stay valid.
+
+use core::fmt;
+use core::ops::{Deref, DerefMut};
+
+use cortex_m::peripheral::SCB;
+use enumflags2::BitFlags;
+
+use crate::gpio::Edge;
+use crate::pac::{EXTI, PWR, RCC};
+use crate::rcc::Enable;
+
+/// Extension trait that constrains the `PWR` peripheral
+pub trait PwrExt {
+    /// Constrains the `PWR` peripheral so it plays nicely with the other abstractions
+    fn constrain(self, rcc: &mut RCC) -> Pwr;
+}
+
+impl PwrExt for PWR {
+    fn constrain(self, rcc: &mut RCC) -> Pwr {
+        // Enable clock.
+        PWR::enable(rcc);
+
+        Pwr(self)
+    }
+}
+
+/// Constrained PWR peripheral
+pub struct Pwr(PWR);
+
+impl Deref for Pwr {
+    type Target = PWR;
+
+    #[inline(always)]
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// The RTC borrows `PWR` to unlock the backup domain
+impl DerefMut for Pwr {
+    #[inline(always)]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for Pwr {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "Pwr(PWR)");
+    }
+}
+
+impl fmt::Debug for Pwr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pwr").finish()
+    }
+}
+
+/// Voltage regulator in Stop mode
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Regulator {
+    /// Main regulator, faster wakeup
+    #[default]
+    Main,
+    /// Low-power regulator, lower consumption
+    LowPower,
+}
+
+/// Stop mode configuration
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct StopConfig {
+    regulator: Regulator,
+    flash_power_down: bool,
+    #[cfg(any(feature = "gpio-f427", feature = "gpio-f446", feature = "gpio-f469"))]
+    under_drive: bool,
+}
+
+impl StopConfig {
+    /// Selects the voltage regulator
+    pub fn regulator(mut self, regulator: Regulator) -> Self {
+        self.regulator = regulator;
+        self
+    }
+
+    /// Powers down the flash, which lowers consumption and delays the wakeup
+    pub fn flash_power_down(mut self, power_down: bool) -> Self {
+        self.flash_power_down = power_down;
+        self
+    }
+
+    /// Puts the regulator in under-drive mode, which lowers consumption further
+    /// and delays the wakeup
+    #[cfg(any(feature = "gpio-f427", feature = "gpio-f446", feature = "gpio-f469"))]
+    pub fn under_drive(mut self, under_drive: bool) -> Self {
+        self.under_drive = under_drive;
+        self
+    }
+}
+
+/// Wakeup pins leaving Standby mode on a rising edge
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WakeupPin {
+    /// PA0
+    Pin1,
+    /// PC13 on STM32F446, PC0 on STM32F410/412/413
+    #[cfg(any(
+        feature = "gpio-f410",
+        feature = "gpio-f412",
+        feature = "gpio-f413",
+        feature = "gpio-f446"
+    ))]
+    Pin2,
+    /// PC1
+    #[cfg(any(feature = "gpio-f410", feature = "gpio-f412", feature = "gpio-f413"))]
+    Pin3,
+}
+
+/// PWR status flags
+#[enumflags2::bitflags]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[repr(u32)]
+pub enum Flag {
+    /// A wakeup event was received
+    Wakeup = 1 << 0,
+    /// The device left Standby mode
+    Standby = 1 << 1,
+    /// VDD is below the PVD threshold, can't be cleared
+    PvdOutput = 1 << 2,
+}
+
+/// PVD threshold, the falling edge threshold is about 0.1 V lower
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PvdLevel {
+    /// 2.0 V rising edge
+    V2_0 = 0,
+    /// 2.1 V rising edge
+    V2_1 = 1,
+    /// 2.3 V rising edge
+    V2_3 = 2,
+    /// 2.5 V rising edge
+    V2_5 = 3,
+    /// 2.6 V rising edge
+    V2_6 = 4,
+    /// 2.7 V rising edge
+    V2_7 = 5,
+    /// 2.8 V rising edge
+    V2_8 = 6,
+    /// 2.9 V rising edge
+    V2_9 = 7,
+}
+
+/// EXTI line of the PVD output
+const PVD_LINE: u32 = 1 << 16;
+
+impl Pwr {
+    /// Returns the `PWR` peripheral
+    pub fn release(self) -> PWR {
+        self.0
+    }
+
+    /// Enters Sleep mode until an interrupt.
+    ///
+    /// With [`set_sleep_on_exit`](Self::set_sleep_on_exit), the core only runs interrupt
+    /// handlers and returns to Sleep mode after them.
+    pub fn enter_sleep(&mut self, scb: &mut SCB) {
+        scb.clear_sleepdeep();
+        cortex_m::asm::dsb();
+        cortex_m::asm::wfi();
+    }
+
+    /// Returns to Sleep mode when leaving the last interrupt handler
+    pub fn set_sleep_on_exit(&mut self, scb: &mut SCB, sleep_on_exit: bool) {
+        if sleep_on_exit {
+            scb.set_sleeponexit();
+        } else {
+            scb.clear_sleeponexit();
+        }
+    }
+
+    /// Enters Stop mode until an EXTI interrupt, e.g. an RTC alarm or wakeup.
+    ///
+    /// The oscillators, the PLLs and the over-drive enabled before are switched on again
+    /// and the system clock source is restored.
+    pub fn enter_stop(&mut self, scb: &mut SCB, config: StopConfig) {
+        let rcc = unsafe { &*RCC::ptr() };
+        let cr = rcc.cr().read();
+        let sw = rcc.cfgr().read().sw().bits();
+        #[cfg(any(feature = "gpio-f427", feature = "gpio-f446", feature = "gpio-f469"))]
+        let over_drive = self.0.cr().read().odswen().bit_is_set();
+
+        self.0.cr().modify(|_, w| {
+            // Stop, not Standby
+            w.pdds().clear_bit();
+            w.lpds().bit(config.regulator == Regulator::LowPower);
+            w.fpds().bit(config.flash_power_down);
+            w.cwuf().set_bit()
+        });
+        #[cfg(any(feature = "gpio-f427", feature = "gpio-f446", feature = "gpio-f469"))]
+        {
+            // Under-drive of the main or the low-power regulator
+            let main = config.under_drive && config.regulator == Regulator::Main;
+            let low_power = config.under_drive && config.regulator == Regulator::LowPower;
+            self.0.cr().modify(|_, w| {
+                if config.under_drive {
+                    w.uden().enabled();
+                } else {
+                    w.uden().disabled();
+                }
+                #[cfg(not(feature = "gpio-f469"))]
+                {
+                    w.mruds().bit(main);
+                    w.lpuds().bit(low_power)
+                }
+                // The PAC names the under-drive bits after the low voltage bits of other parts
+                #[cfg(feature = "gpio-f469")]
+                {
+                    w.mrlvds().bit(main);
+                    w.lplvds().bit(low_power)
+                }
+            });
+        }
+
+        scb.set_sleepdeep();
+        cortex_m::asm::dsb();
+        cortex_m::asm::wfi();
+        scb.clear_sleepdeep();
+
+        #[cfg(any(feature = "gpio-f427", feature = "gpio-f446", feature = "gpio-f469"))]
+        if config.under_drive {
+            // Write 1 to clear
+            self.0.csr().modify(|_, w| w.udrdy().ready());
+        }
+
+        // HSE first, the PLLs may use it
+        if cr.hseon().bit_is_set() {
+            rcc.cr().modify(|_, w| w.hseon().set_bit());
+            while rcc.cr().read().hserdy().bit_is_clear() {}
+        }
+        if cr.pllon().bit_is_set() {
+            rcc.cr().modify(|_, w| w.pllon().set_bit());
+            while rcc.cr().read().pllrdy().bit_is_clear() {}
+        }
+        #[cfg(not(feature = "gpio-f410"))]
+        if cr.plli2son().bit_is_set() {
+            rcc.cr().modify(|_, w| w.plli2son().set_bit());
+            while rcc.cr().read().plli2srdy().bit_is_clear() {}
+        }
+        #[cfg(feature = "sai")]
+        #[cfg(not(feature = "gpio-f413"))]
+        if cr.pllsaion().bit_is_set() {
+            rcc.cr().modify(|_, w| w.pllsaion().set_bit());
+            while rcc.cr().read().pllsairdy().bit_is_clear() {}
+        }
+        #[cfg(any(feature = "gpio-f427", feature = "gpio-f446", feature = "gpio-f469"))]
+        if over_drive {
+            self.0.cr().modify(|_, w| w.oden().set_bit());
+            while self.0.csr().read().odrdy().bit_is_clear() {}
+            self.0.cr().modify(|_, w| w.odswen().set_bit());
+            while self.0.csr().read().odswrdy().bit_is_clear() {}
+        }
+        rcc.cfgr().modify(|_, w| unsafe { w.sw().bits(sw) });
+        while rcc.cfgr().read().sws().bits() != sw {}
+    }
+
+    /// Enters Standby mode, which is left through a reset.
+    ///
+    /// Only the backup domain and the RTC keep running. A wakeup pin, an RTC alarm, wakeup,
+    /// tamper or time stamp event, the NRST pin or the IWDG reset the device.
+    pub fn enter_standby(&mut self, scb: &mut SCB) -> ! {
+        self.0.cr().modify(|_, w| {
+            w.pdds().set_bit();
+            w.csbf().set_bit();
+            w.cwuf().set_bit()
+        });
+        scb.set_sleepdeep();
+        cortex_m::asm::dsb();
+        loop {
+            cortex_m::asm::wfi();
+        }
+    }
+
+    /// Enables a wakeup pin, which is used as input in Standby mode.
+    ///
+    /// Enabling it while the pin is high sets the wakeup flag.
+    pub fn enable_wakeup_pin(&mut self, pin: WakeupPin, enable: bool) {
+        self.0.csr().modify(|_, w| match pin {
+            #[cfg(not(any(
+                feature = "gpio-f410",
+                feature = "gpio-f412",
+                feature = "gpio-f413",
+                feature = "gpio-f446"
+            )))]
+            WakeupPin::Pin1 => w.ewup().bit(enable),
+            #[cfg(any(
+                feature = "gpio-f410",
+                feature = "gpio-f412",
+                feature = "gpio-f413",
+                feature = "gpio-f446"
+            ))]
+            WakeupPin::Pin1 => w.ewup1().bit(enable),
+            #[cfg(any(
+                feature = "gpio-f410",
+                feature = "gpio-f412",
+                feature = "gpio-f413",
+                feature = "gpio-f446"
+            ))]
+            WakeupPin::Pin2 => w.ewup2().bit(enable),
+            #[cfg(any(feature = "gpio-f410", feature = "gpio-f412", feature = "gpio-f413"))]
+            WakeupPin::Pin3 => w.ewup3().bit(enable),
+        });
+    }
+}
+
+impl Pwr {
+    /// Enables the PVD with the threshold `level`
+    pub fn enable_pvd(&mut self, level: PvdLevel) {
+        self.0.cr().modify(|_, w| unsafe {
+            w.pls().bits(level as u8);
+            w.pvde().set_bit()
+        });
+    }
+
+    /// Disables the PVD
+    pub fn disable_pvd(&mut self) {
+        self.0.cr().modify(|_, w| w.pvde().clear_bit());
+    }
+
+    /// Returns true if VDD is below the PVD threshold
+    pub fn is_below_pvd_threshold(&self) -> bool {
+        self.0.csr().read().pvdo().bit_is_set()
+    }
+
+    /// Generate interrupt when VDD falls below the threshold (rising edge of the PVD output),
+    /// rises above it (falling edge) or both
+    pub fn trigger_pvd_on_edge(&mut self, exti: &mut EXTI, edge: Edge) {
+        let (rising, falling) = match edge {
+            Edge::Rising => (true, false),
+            Edge::Falling => (false, true),
+            Edge::RisingFalling => (true, true),
+        };
+        exti.rtsr().modify(|r, w| unsafe {
+            w.bits(if rising {
+                r.bits() | PVD_LINE
+            } else {
+                r.bits() & !PVD_LINE
+            })
+        });
+        exti.ftsr().modify(|r, w| unsafe {
+            w.bits(if falling {
+                r.bits() | PVD_LINE
+            } else {
+                r.bits() & !PVD_LINE
+            })
+        });
+    }
+
+    /// Enable the `PVD` interrupt of EXTI line 16
+    pub fn enable_pvd_interrupt(&mut self, exti: &mut EXTI) {
+        exti.imr()
+            .modify(|r, w| unsafe { w.bits(r.bits() | PVD_LINE) });
+    }
+
+    /// Disable the `PVD` interrupt of EXTI line 16
+    pub fn disable_pvd_interrupt(&mut self, exti: &mut EXTI) {
+        exti.imr()
+            .modify(|r, w| unsafe { w.bits(r.bits() & !PVD_LINE) });
+    }
+
+    /// Clear the interrupt pending bit of EXTI line 16
+    pub fn clear_pvd_interrupt_pending_bit(&mut self) {
+        let exti = unsafe { &*EXTI::ptr() };
+        // Write 1 to clear
+        exti.pr().write(|w| unsafe { w.bits(PVD_LINE) });
+    }
+
+    /// Reads the interrupt pending bit of EXTI line 16
+    pub fn check_pvd_interrupt(&self) -> bool {
+        let exti = unsafe { &*EXTI::ptr() };
+        exti.pr().read().bits() & PVD_LINE != 0
+    }
+}
+
+impl crate::ClearFlags for Pwr {
+    type Flag = Flag;
+
+    fn clear_flags(&mut self, flags: impl Into<BitFlags<Self::Flag>>) {
+        let flags = flags.into();
+        self.0.cr().modify(|_, w| {
+            if flags.contains(Flag::Wakeup) {
+                w.cwuf().set_bit();
+            }
+            if flags.contains(Flag::Standby) {
+                w.csbf().set_bit();
+            }
+            w
+        });
+    }
+}
+
+impl crate::ReadFlags for Pwr {
+    type Flag = Flag;
+
+    #[inline(always)]
+    fn flags(&self) -> BitFlags<Self::Flag> {
+        BitFlags::from_bits_truncate(self.0.csr().read().bits())
+    }
+}
@@ -156,6 +156,7 @@ cfg_if! {feature = "fsmc" => {
 
 bus! {
     PWR => (APB1, 28),
+    WWDG => (APB1, 11),
 }
 
 bus! {
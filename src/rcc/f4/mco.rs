@@ -0,0 +1,116 @@
+//! Microcontroller clock outputs
+//!
+//! ```
+//! let mut rcc = dp.RCC.constrain();
+//! // 8 MHz on PA8 before the PLL is enabled
+//! let mco1 = rcc.mco1(gpioa.pa8, Mco1Source::Hse, McoPrescaler::Div1);
+//! let mut rcc = rcc.freeze(Config::hse(8.MHz()).sysclk(168.MHz()));
+//! // 168 MHz / 4 on PC9
+//! let mco2 = rcc.mco2(gpioc.pc9, Mco2Source::Sysclk, McoPrescaler::Div4);
+//! ```
+
+use super::Rcc;
+use crate::gpio::{alt::rcc as alt, PinSpeed, Speed};
+
+/// Clock source of MCO1 (PA8)
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mco1Source {
+    /// Internal high speed oscillator
+    Hsi = 0b00,
+    /// External low speed oscillator
+    Lse = 0b01,
+    /// External high speed oscillator
+    Hse = 0b10,
+    /// Main PLL output
+    Pll = 0b11,
+}
+
+/// Clock source of MCO2 (PC9)
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mco2Source {
+    /// System clock
+    Sysclk = 0b00,
+    /// I2S PLL output
+    #[cfg(not(feature = "gpio-f410"))]
+    Plli2s = 0b01,
+    /// External high speed oscillator
+    Hse = 0b10,
+    /// Main PLL output
+    Pll = 0b11,
+}
+
+/// Division of the output clock
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum McoPrescaler {
+    /// No division
+    Div1 = 0b000,
+    /// Division by 2
+    Div2 = 0b100,
+    /// Division by 3
+    Div3 = 0b101,
+    /// Division by 4
+    Div4 = 0b110,
+    /// Division by 5
+    Div5 = 0b111,
+}
+
+/// Clock output owning its pin
+pub struct Mco<PIN> {
+    pin: PIN,
+}
+
+impl<PIN> Mco<PIN> {
+    /// Returns the pin, the clock is output until the pin mode changes
+    pub fn release(self) -> PIN {
+        self.pin
+    }
+}
+
+impl Rcc {
+    /// Outputs `source` divided by `prescaler` on MCO1.
+    ///
+    /// Changing the source while it runs may glitch, the reference manual recommends
+    /// configuring it before enabling the oscillators and the PLL.
+    pub fn mco1(
+        &mut self,
+        pin: impl Into<alt::Mco1>,
+        source: Mco1Source,
+        prescaler: McoPrescaler,
+    ) -> Mco<alt::Mco1> {
+        self.rb.cfgr().modify(|r, w| unsafe {
+            w.bits(
+                r.bits() & !(0b11 << 21 | 0b111 << 24)
+                    | (source as u32) << 21
+                    | (prescaler as u32) << 24,
+            )
+        });
+        Mco {
+            pin: pin.into().speed(Speed::VeryHigh),
+        }
+    }
+
+    /// Outputs `source` divided by `prescaler` on MCO2.
+    ///
+    /// Changing the source while it runs may glitch, the reference manual recommends
+    /// configuring it before enabling the oscillators and the PLLs.
+    pub fn mco2(
+        &mut self,
+        pin: impl Into<alt::Mco2>,
+        source: Mco2Source,
+        prescaler: McoPrescaler,
+    ) -> Mco<alt::Mco2> {
+        self.rb.cfgr().modify(|r, w| unsafe {
+            w.bits(
+                r.bits() & !(0b11 << 30 | 0b111 << 27)
+                    | (source as u32) << 30
+                    | (prescaler as u32) << 27,
+            )
+        });
+        Mco {
+            pin: pin.into().speed(Speed::VeryHigh),
+        }
+    }
+}
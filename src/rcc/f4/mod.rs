@@ -10,6 +10,9 @@ mod pll;
 
 mod enable;
 
+mod mco;
+pub use mco::*;
+
 /// Built-in high speed clock frequency
 pub const HSI: u32 = 16_000_000; // Hz
 
@@ -76,6 +79,33 @@ pub const PCLK2_MAX: u32 = cfg_select! {
 /// Maximum APB1 peripheral clock frequency
 pub const PCLK1_MAX: u32 = PCLK2_MAX / 2;
 
+/// Regulator voltage scaling, trades the maximum AHB clock for consumption
+#[cfg(any(feature = "gpio-f427", feature = "gpio-f446", feature = "gpio-f469"))]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VoltageScale {
+    /// HCLK up to 168 MHz, 180 MHz with over-drive
+    Scale1 = 0b11,
+    /// HCLK up to 144 MHz, 168 MHz with over-drive
+    Scale2 = 0b10,
+    /// HCLK up to 120 MHz, without over-drive
+    Scale3 = 0b01,
+}
+
+#[cfg(any(feature = "gpio-f427", feature = "gpio-f446", feature = "gpio-f469"))]
+impl VoltageScale {
+    /// Maximum AHB clock frequency
+    pub const fn hclk_max(self, over_drive: bool) -> u32 {
+        match (self, over_drive) {
+            (Self::Scale1, false) => 168_000_000,
+            (Self::Scale1, true) => 180_000_000,
+            (Self::Scale2, false) => 144_000_000,
+            (Self::Scale2, true) => 168_000_000,
+            (Self::Scale3, _) => 120_000_000,
+        }
+    }
+}
+
 #[derive(Clone, Copy, Debug)]
 pub struct Config {
     hse: Option<u32>,
@@ -99,6 +129,11 @@ pub struct Config {
     sai1_clk: Option<u32>,
     #[cfg(feature = "sai")]
     sai2_clk: Option<u32>,
+
+    #[cfg(any(feature = "gpio-f427", feature = "gpio-f446", feature = "gpio-f469"))]
+    voltage_scale: VoltageScale,
+    #[cfg(any(feature = "gpio-f427", feature = "gpio-f446", feature = "gpio-f469"))]
+    over_drive: Option<bool>,
 }
 
 impl Default for Config {
@@ -130,6 +165,11 @@ impl Config {
         sai1_clk: None,
         #[cfg(feature = "sai")]
         sai2_clk: None,
+
+        #[cfg(any(feature = "gpio-f427", feature = "gpio-f446", feature = "gpio-f469"))]
+        voltage_scale: VoltageScale::Scale1,
+        #[cfg(any(feature = "gpio-f427", feature = "gpio-f446", feature = "gpio-f469"))]
+        over_drive: None,
     };
 
     pub fn hsi() -> Self {
@@ -194,6 +234,33 @@ impl Config {
         self.i2s_ckin = Some(freq.raw());
         self
     }
+
+    /// Selects the regulator voltage scale, `Scale1` by default.
+    ///
+    /// The AHB clock is checked against the maximum of the scale.
+    ///
+    /// # Panics
+    ///
+    /// * `freeze` panics for `Scale3` together with over-drive, which `Scale3` doesn't support.
+    #[cfg(any(feature = "gpio-f427", feature = "gpio-f446", feature = "gpio-f469"))]
+    pub fn voltage_scale(mut self, scale: VoltageScale) -> Self {
+        self.voltage_scale = scale;
+        self
+    }
+
+    /// Enables or disables the regulator over-drive with the PLL.
+    ///
+    /// By default it is enabled only when the AHB clock exceeds the maximum of the voltage scale.
+    ///
+    /// # Panics
+    ///
+    /// * `freeze` panics when enabled, or required by the AHB clock, with
+    ///   [`VoltageScale::Scale3`], which doesn't support over-drive.
+    #[cfg(any(feature = "gpio-f427", feature = "gpio-f446", feature = "gpio-f469"))]
+    pub fn over_drive(mut self, enable: bool) -> Self {
+        self.over_drive = Some(enable);
+        self
+    }
 }
 
 impl Config {
@@ -392,6 +459,17 @@ impl Rcc {
         // Calculate real AHB clock
         let hclk = sysclk / hpre_div;
 
+        #[cfg(any(feature = "gpio-f427", feature = "gpio-f446", feature = "gpio-f469"))]
+        let over_drive = rcc_cfg
+            .over_drive
+            .unwrap_or(hclk > rcc_cfg.voltage_scale.hclk_max(false));
+        #[cfg(any(feature = "gpio-f427", feature = "gpio-f446", feature = "gpio-f469"))]
+        {
+            // Over-drive is not available in scale 3
+            assert!(!over_drive || rcc_cfg.voltage_scale != VoltageScale::Scale3);
+            assert!(unchecked || hclk <= rcc_cfg.voltage_scale.hclk_max(over_drive));
+        }
+
         let pclk1 = rcc_cfg
             .pclk1
             .unwrap_or_else(|| crate::min_u32(PCLK1_MAX, hclk));
@@ -440,18 +518,26 @@ impl Rcc {
         }
 
         if plls.use_pll {
-            // Enable PLL
-            rcc.cr().modify(|_, w| w.pllon().set_bit());
-
-            // Enable voltage regulator overdrive if HCLK is above the limit
+            // The voltage scale is applied when the PLL is enabled
             #[cfg(any(feature = "gpio-f427", feature = "gpio-f446", feature = "gpio-f469"))]
-            if hclk > 168_000_000 {
+            {
                 // Enable clock for PWR peripheral
                 rcc.apb1enr().modify(|_, w| w.pwren().set_bit());
 
                 // Stall the pipeline to work around erratum 2.1.13 (DM00037591)
                 cortex_m::asm::dsb();
 
+                let pwr = unsafe { &*crate::pac::PWR::ptr() };
+                pwr.cr()
+                    .modify(|_, w| unsafe { w.vos().bits(rcc_cfg.voltage_scale as u8) });
+            }
+
+            // Enable PLL
+            rcc.cr().modify(|_, w| w.pllon().set_bit());
+
+            // Enable voltage regulator overdrive if requested or HCLK is above the limit
+            #[cfg(any(feature = "gpio-f427", feature = "gpio-f446", feature = "gpio-f469"))]
+            if over_drive {
                 let pwr = unsafe { &*crate::pac::PWR::ptr() };
                 pwr.cr().modify(|_, w| w.oden().set_bit());
                 while pwr.csr().read().odrdy().bit_is_clear() {}
@@ -45,6 +45,7 @@ pub use f4::*;
 use crate::pac::rcc::{self, RegisterBlock as RccRB};
 use crate::pac::RCC;
 use core::ops::{Deref, DerefMut};
+use enumflags2::BitFlags;
 use fugit::HertzU32 as Hertz;
 
 /// Constrained RCC peripheral
@@ -88,6 +89,45 @@ impl RccExt for RCC {
     }
 }
 
+/// Reset flags of RCC_CSR
+///
+/// A power-on reset also sets the pin and brown-out flags.
+#[enumflags2::bitflags]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[repr(u32)]
+pub enum ResetReason {
+    /// Brown-out reset, or power-on/power-down reset
+    BrownOut = 1 << 25,
+    /// NRST pin reset
+    Pin = 1 << 26,
+    /// Power-on/power-down reset
+    PowerOn = 1 << 27,
+    /// Software reset, e.g. `SCB::sys_reset`
+    Software = 1 << 28,
+    /// Independent watchdog reset
+    IndependentWatchdog = 1 << 29,
+    /// Window watchdog reset
+    WindowWatchdog = 1 << 30,
+    /// Reset entering Standby or Stop mode, see the `nRST_STDBY` and `nRST_STOP` option bytes
+    LowPower = 1 << 31,
+}
+
+impl Rcc {
+    /// Returns the reset flags set since they were last cleared.
+    ///
+    /// The flags are kept until [`clear_reset_reason`](Self::clear_reset_reason) or a
+    /// power-on reset, so they are read early in `main`, before or after `freeze`.
+    pub fn reset_reason(&self) -> BitFlags<ResetReason> {
+        BitFlags::from_bits_truncate(self.rb.csr().read().bits())
+    }
+
+    /// Clears the reset flags, so that the next reset reports only its own cause
+    pub fn clear_reset_reason(&mut self) {
+        self.rb.csr().modify(|_, w| w.rmvf().set_bit());
+    }
+}
+
 /// Common trait for most of peripherals
 pub trait Instance:
     crate::Ptr + crate::Steal + Enable + Reset + RccBus<Bus: BusClock> + Deref<Target = Self::RB>
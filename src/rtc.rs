@@ -7,6 +7,7 @@ use crate::pac::rtc::{dr, tr};
 use crate::pac::{self, PWR, RCC, RTC};
 use crate::rcc::Enable;
 use core::fmt;
+use enumflags2::BitFlags;
 use fugit::RateExtU32;
 use time::{Date, PrimitiveDateTime, Time, Weekday};
 
@@ -24,6 +25,8 @@ pub enum Event {
     AlarmB,
     Wakeup,
     Timestamp,
+    /// Tamper detected on one of the enabled tamper inputs
+    Tamper,
 }
 
 #[cfg_attr(feature = "defmt", derive(defmt::Format))]
@@ -61,6 +64,194 @@ impl From<Weekday> for AlarmDay {
     }
 }
 
+/// Alarm fields ignored when matching the calendar
+#[enumflags2::bitflags]
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[repr(u32)]
+pub enum AlarmMask {
+    Seconds = 1 << 7,
+    Minutes = 1 << 15,
+    Hours = 1 << 23,
+    /// Date or weekday
+    Day = 1 << 31,
+}
+
+/// Smooth calibration of the RTC clock
+///
+/// Over a calibration cycle of 2<sup>20</sup> RTCCLK pulses (32 s with a 32.768 kHz clock),
+/// `minus` pulses are masked and 512 pulses are inserted if `plus` is set.
+/// The resolution is 0.954 ppm, the range -487.1 ppm to +488.5 ppm.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub struct SmoothCalibration {
+    /// Increases the frequency by 488.5 ppm
+    pub plus: bool,
+    /// Number of masked pulses (0-511), each decreases the frequency by 0.954 ppm
+    pub minus: u16,
+}
+
+impl SmoothCalibration {
+    /// Pulses per calibration cycle
+    const CYCLE: f32 = (1 << 20) as f32;
+
+    /// Calibration compensating the measured frequency error of the RTC clock,
+    /// `ppm` is positive when the clock is too fast
+    pub fn from_ppm(ppm: f32) -> Result<Self, Error> {
+        // Pulses to mask, rounded to the nearest
+        let pulses = ppm * Self::CYCLE / 1_000_000.;
+        let pulses = if pulses >= 0. {
+            (pulses + 0.5) as i32
+        } else {
+            (pulses - 0.5) as i32
+        };
+        let (plus, minus) = match pulses {
+            0..=511 => (false, pulses),
+            // 512 inserted pulses
+            -512..=-1 => (true, 512 + pulses),
+            _ => return Err(Error::InvalidInputData),
+        };
+        Ok(Self {
+            plus,
+            minus: minus as u16,
+        })
+    }
+
+    /// Frequency correction in ppm
+    pub fn ppm(&self) -> f32 {
+        let inserted = if self.plus { 512. } else { 0. };
+        let pulses = inserted - self.minus as f32;
+        pulses * 1_000_000. / (Self::CYCLE - pulses)
+    }
+}
+
+/// Smooth calibration cycle period
+///
+/// The shorter cycles ignore the lower bits of `SmoothCalibration::minus`
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum CalibrationCycle {
+    /// 8 s, the 2 lower bits are ignored
+    Seconds8,
+    /// 16 s, the lower bit is ignored
+    Seconds16,
+    Seconds32,
+}
+
+/// Coarse digital calibration of the calendar clock
+///
+/// The ck_apre clock is adjusted during the first 2 × steps minutes of every 64 minutes
+/// cycle, `prediv_a` must be at least 6.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum CoarseCalibration {
+    /// Increases the frequency by about 4 ppm per step (0-31), up to +126 ppm
+    Positive(u8),
+    /// Decreases the frequency by about 2 ppm per step (0-31), down to -63 ppm
+    Negative(u8),
+}
+
+/// Frequency of the calibration output on `AFO_CALIB` (PC13)
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum CalibrationOutput {
+    /// RTCCLK / 64, with `prediv_a` set to 127
+    Hz512,
+    /// ck_spre, the calendar clock
+    Hz1,
+}
+
+/// Number of backup registers
+pub const BACKUP_REGISTERS: usize = 20;
+
+/// Tamper inputs
+///
+/// `TAMP2` is not available on all devices.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum TamperInput {
+    /// `AFI_TAMP1` (PC13)
+    Tamper1,
+    /// `AFI_TAMP2` (PI8)
+    Tamper2,
+}
+
+impl TamperInput {
+    /// TAMPxE bit of TAFCR, TAMPxTRG is the next bit
+    const fn enable_bit(self) -> u8 {
+        match self {
+            Self::Tamper1 => 0,
+            Self::Tamper2 => 3,
+        }
+    }
+
+    /// TAMPxF bit of ISR
+    const fn flag_bit(self) -> u8 {
+        match self {
+            Self::Tamper1 => 13,
+            Self::Tamper2 => 14,
+        }
+    }
+}
+
+/// Tamper detection of a tamper input
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub enum TamperTrigger {
+    /// Edge detection, requires `TamperFilter::Edge`
+    RisingEdge,
+    /// Edge detection, requires `TamperFilter::Edge`
+    FallingEdge,
+    /// Level detection, requires a sampling filter
+    LowLevel,
+    /// Level detection, requires a sampling filter
+    HighLevel,
+}
+
+/// Tamper detection mode, common to the tamper inputs
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Default, Eq, PartialEq, Copy, Clone)]
+pub enum TamperFilter {
+    /// Edge detection
+    #[default]
+    Edge = 0,
+    /// Level detection after 2 consecutive samples
+    Samples2 = 1,
+    /// Level detection after 4 consecutive samples
+    Samples4 = 2,
+    /// Level detection after 8 consecutive samples
+    Samples8 = 3,
+}
+
+/// Tamper inputs configuration
+///
+/// The sampling frequency, precharge and pull-up are used by the level detection.
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+pub struct TamperConfig {
+    pub filter: TamperFilter,
+    /// Sampling frequency: RTCCLK / 2<sup>(15 - `sampling`)</sup>, from 0 (1 Hz with LSE) to 7 (128 Hz)
+    pub sampling: u8,
+    /// Precharge duration in RTCCLK cycles: 2<sup>`precharge`</sup>, from 0 to 3
+    pub precharge: u8,
+    /// Precharges the inputs with the internal pull-up before sampling
+    pub pull_up: bool,
+    /// Saves the timestamp on tamper detection
+    pub timestamp: bool,
+}
+
+impl Default for TamperConfig {
+    fn default() -> Self {
+        Self {
+            filter: TamperFilter::Edge,
+            sampling: 0,
+            precharge: 0,
+            pull_up: true,
+            timestamp: false,
+        }
+    }
+}
+
 /// RTC clock source LSE oscillator clock (type state)
 pub struct Lse;
 /// RTC clock source LSI oscillator clock (type state)
@@ -269,6 +460,84 @@ impl Rtc {
         });
     }
 
+    /// Trims the RTC clock with the smooth calibration.
+    ///
+    /// Should not be used together with the coarse calibration.
+    pub fn set_smooth_calibration(
+        &mut self,
+        calibration: SmoothCalibration,
+        cycle: CalibrationCycle,
+    ) -> Result<(), Error> {
+        if calibration.minus > 0x1FF {
+            return Err(Error::InvalidInputData);
+        }
+        self.modify(false, |regs| {
+            // wait until a previous calibration is applied
+            while regs.isr().read().recalpf().bit_is_set() {}
+            regs.calr().write(|w| {
+                w.calp().bit(calibration.plus);
+                w.calw8().bit(cycle == CalibrationCycle::Seconds8);
+                w.calw16().bit(cycle == CalibrationCycle::Seconds16);
+                w.calm().set(calibration.minus)
+            });
+        });
+        Ok(())
+    }
+
+    /// Trims the calendar clock with the coarse digital calibration, `None` disables it.
+    ///
+    /// Should not be used together with the smooth calibration.
+    pub fn set_coarse_calibration(
+        &mut self,
+        calibration: Option<CoarseCalibration>,
+    ) -> Result<(), Error> {
+        let (negative, steps) = match calibration {
+            Some(CoarseCalibration::Positive(steps)) => (false, steps),
+            Some(CoarseCalibration::Negative(steps)) => (true, steps),
+            None => (false, 0),
+        };
+        if steps > 31 || (calibration.is_some() && self.regs.prer().read().prediv_a().bits() < 6) {
+            return Err(Error::InvalidInputData);
+        }
+        self.modify(true, |regs| {
+            regs.calibr().write(|w| {
+                w.dcs().bit(negative);
+                unsafe { w.dc().bits(steps) }
+            });
+            // Set/clear CR - Bit 7 (DCE)
+            unsafe {
+                if calibration.is_some() {
+                    bb::set(regs.cr(), 7);
+                } else {
+                    bb::clear(regs.cr(), 7);
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Outputs the calibration clock on `AFO_CALIB` (PC13) to measure the RTC clock
+    pub fn enable_calibration_output(&mut self, output: CalibrationOutput) {
+        self.modify(false, |regs| unsafe {
+            // Set/clear CR - Bit 19 (COSEL)
+            if output == CalibrationOutput::Hz1 {
+                bb::set(regs.cr(), 19);
+            } else {
+                bb::clear(regs.cr(), 19);
+            }
+            // Set CR - Bit 23 (COE)
+            bb::set(regs.cr(), 23);
+        });
+    }
+
+    /// Stops the calibration output
+    pub fn disable_calibration_output(&mut self) {
+        self.modify(false, |regs| unsafe {
+            // Clear CR - Bit 23 (COE)
+            bb::clear(regs.cr(), 23);
+        });
+    }
+
     /// As described in Section 27.3.7 in RM0316,
     /// this function is used to disable write protection
     /// when modifying an RTC register
@@ -625,8 +894,106 @@ impl Rtc {
         )
     }
 
+    /// Reads the backup register `register` (0-19)
+    ///
+    /// The backup registers keep their value in standby mode and with VBAT,
+    /// they are reset on tamper detection and backup domain reset.
+    ///
+    /// # Panics
+    ///
+    /// * When `register` is not lower than `BACKUP_REGISTERS`.
+    pub fn read_backup_register(&self, register: usize) -> u32 {
+        assert!(register < BACKUP_REGISTERS);
+        self.regs.bkpr(register).read().bits()
+    }
+
+    /// Writes `value` to the backup register `register` (0-19)
+    ///
+    /// # Panics
+    ///
+    /// * When `register` is not lower than `BACKUP_REGISTERS`.
+    pub fn write_backup_register(&mut self, register: usize, value: u32) {
+        assert!(register < BACKUP_REGISTERS);
+        self.regs.bkpr(register).write(|w| unsafe { w.bits(value) });
+    }
+
+    /// Configures the tamper detection common to the tamper inputs
+    ///
+    /// The tamper inputs must be disabled.
+    pub fn configure_tamper(&mut self, config: TamperConfig) -> Result<(), Error> {
+        if config.sampling > 7 || config.precharge > 3 {
+            return Err(Error::InvalidInputData);
+        }
+        self.regs.tafcr().modify(|r, w| unsafe {
+            w.bits(
+                r.bits() & !0xFF80
+                    | u32::from(config.timestamp) << 7
+                    | (config.sampling as u32) << 8
+                    | (config.filter as u32) << 11
+                    | (config.precharge as u32) << 13
+                    | u32::from(!config.pull_up) << 15,
+            )
+        });
+        Ok(())
+    }
+
+    /// Enables the tamper detection on `input`, a detection erases the backup registers.
+    ///
+    /// Returns an error if `trigger` doesn't match the configured filter.
+    pub fn enable_tamper(
+        &mut self,
+        input: TamperInput,
+        trigger: TamperTrigger,
+    ) -> Result<(), Error> {
+        let edge = self.regs.tafcr().read().bits() & (0b11 << 11) == 0;
+        // TAMPxTRG: rising edge or low level if cleared
+        let trg = match trigger {
+            TamperTrigger::RisingEdge if edge => false,
+            TamperTrigger::FallingEdge if edge => true,
+            TamperTrigger::LowLevel if !edge => false,
+            TamperTrigger::HighLevel if !edge => true,
+            _ => return Err(Error::InvalidInputData),
+        };
+        let bit = input.enable_bit();
+        // The trigger is selected before enabling the detection
+        self.regs.tafcr().modify(|r, w| unsafe {
+            w.bits(r.bits() & !(0b11 << bit) | u32::from(trg) << (bit + 1))
+        });
+        self.clear_tamper_flag(input);
+        self.regs
+            .tafcr()
+            .modify(|r, w| unsafe { w.bits(r.bits() | 1 << bit) });
+        Ok(())
+    }
+
+    /// Disables the tamper detection on `input`
+    pub fn disable_tamper(&mut self, input: TamperInput) {
+        let bit = input.enable_bit();
+        self.regs
+            .tafcr()
+            .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << bit)) });
+    }
+
+    /// Returns `true` if a tamper was detected on `input`
+    pub fn is_tamper_detected(&self, input: TamperInput) -> bool {
+        self.regs.isr().read().bits() & (1 << input.flag_bit()) != 0
+    }
+
+    /// Clears the tamper detection flag of `input`
+    pub fn clear_tamper_flag(&mut self, input: TamperInput) {
+        let bit = input.flag_bit();
+        self.modify(false, |regs| {
+            // The other flags are not affected by writing 1
+            regs.isr()
+                .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << bit)) });
+        });
+    }
+
     /// Sets the time at which an alarm will be triggered
     /// This also clears the alarm flag if it is set
+    ///
+    /// The seconds, minutes and hours are matched, the day is masked with `AlarmDay::EveryDay`.
+    /// The sub-seconds are matched if `time` has a nonzero fraction of second.
     pub fn set_alarm(
         &mut self,
         alarm: Alarm,
@@ -642,13 +1009,13 @@ impl Rtc {
         let (ht, hu) = bcd2_encode(time.hour().into())?;
         let (mnt, mnu) = bcd2_encode(time.minute().into())?;
         let (st, su) = bcd2_encode(time.second().into())?;
+        let prediv_s = self.regs.prer().read().prediv_s().bits();
+        let (ss, ss_bits) = match time.nanosecond() {
+            0 => (0, 0),
+            nano => (nano_to_ss(nano, prediv_s), 15),
+        };
 
-        self.modify(false, |rtc| {
-            unsafe {
-                bb::clear(rtc.cr(), 8 + (alarm as u8));
-                bb::clear(rtc.isr(), 8 + (alarm as u8));
-            }
-            while rtc.isr().read().bits() & (1 << (alarm as u32)) == 0 {}
+        self.modify_alarm(alarm, |rtc| {
             let reg = rtc.alrmr(alarm as usize);
             reg.modify(|_, w| {
                 w.dt().set(dt);
@@ -661,18 +1028,67 @@ impl Rtc {
                 w.su().set(su);
                 w.pm().clear_bit();
                 w.wdsel().bit(wdsel);
+                w.msk1().clear_bit();
+                w.msk2().clear_bit();
+                w.msk3().clear_bit();
                 w.msk4().bit(daymask)
             });
-            // subsecond alarm not implemented
-            // would need a conversion method between `time.micros` and RTC ticks
-            // write the SS value and mask to `rtc.alrmssr[alarm]`
+            rtc.alrmssr(alarm as usize)
+                .write(|w| unsafe { w.bits(alarm_ssr(ss, ss_bits)) });
+        });
+        Ok(())
+    }
+
+    /// Ignores the fields in `mask` when matching the alarm, e.g. masking the minutes,
+    /// hours and day of an alarm set at 00:00:30 triggers it once per minute.
+    ///
+    /// This also clears the alarm flag if it is set
+    pub fn set_alarm_mask(&mut self, alarm: Alarm, mask: impl Into<BitFlags<AlarmMask>>) {
+        let mask = mask.into();
+        self.modify_alarm(alarm, |rtc| {
+            rtc.alrmr(alarm as usize).modify(|r, w| unsafe {
+                w.bits(r.bits() & !BitFlags::<AlarmMask>::ALL.bits() | mask.bits())
+            });
+        });
+    }
+
+    /// Matches the `bits` least significant bits (0 to 15) of the sub-second counter
+    /// with `ss`, 0 bits disables the sub-second matching.
+    ///
+    /// The sub-second counter counts down from `prediv_s` every second. With all the
+    /// fields masked, the alarm triggers every 2<sup>`bits`</sup> counter ticks, e.g. every
+    /// 250 ms with the default `prediv_s` of 255 and 6 bits.
+    ///
+    /// This also clears the alarm flag if it is set
+    pub fn set_alarm_subseconds(&mut self, alarm: Alarm, ss: u16, bits: u8) -> Result<(), Error> {
+        if bits > 15 || ss > 0x7FFF {
+            return Err(Error::InvalidInputData);
+        }
+        self.modify_alarm(alarm, |rtc| {
+            rtc.alrmssr(alarm as usize)
+                .write(|w| unsafe { w.bits(alarm_ssr(ss, bits)) });
+        });
+        Ok(())
+    }
+
+    /// Disables the alarm while `closure` updates its registers
+    fn modify_alarm<F>(&mut self, alarm: Alarm, mut closure: F)
+    where
+        F: FnMut(&mut RTC),
+    {
+        self.modify(false, |rtc| {
+            unsafe {
+                bb::clear(rtc.cr(), 8 + (alarm as u8));
+                bb::clear(rtc.isr(), 8 + (alarm as u8));
+            }
+            while rtc.isr().read().bits() & (1 << (alarm as u32)) == 0 {}
+            closure(rtc);
 
             // enable alarm and reenable interrupt if it was enabled
             unsafe {
                 bb::set(rtc.cr(), 8 + (alarm as u8));
             }
         });
-        Ok(())
     }
 
     /// Start listening for `event`
@@ -702,6 +1118,13 @@ impl Rtc {
                 exti.imr().modify(|_, w| w.mr21().set_bit());
                 regs.cr().modify(|_, w| w.tsie().set_bit());
             }
+            Event::Tamper => {
+                exti.rtsr().modify(|_, w| w.tr21().enabled());
+                exti.imr().modify(|_, w| w.mr21().set_bit());
+                // Set TAFCR - Bit 2 (TAMPIE)
+                regs.tafcr()
+                    .modify(|r, w| unsafe { w.bits(r.bits() | 1 << 2) });
+            }
         });
     }
 
@@ -729,6 +1152,13 @@ impl Rtc {
                 exti.imr().modify(|_, w| w.mr21().clear_bit());
                 exti.rtsr().modify(|_, w| w.tr21().disabled());
             }
+            Event::Tamper => {
+                // Clear TAFCR - Bit 2 (TAMPIE)
+                regs.tafcr()
+                    .modify(|r, w| unsafe { w.bits(r.bits() & !(1 << 2)) });
+                exti.imr().modify(|_, w| w.mr21().clear_bit());
+                exti.rtsr().modify(|_, w| w.tr21().disabled());
+            }
         });
     }
 
@@ -739,6 +1169,10 @@ impl Rtc {
             Event::AlarmB => self.regs.isr().read().alrbf().bit_is_set(),
             Event::Wakeup => self.regs.isr().read().wutf().bit_is_set(),
             Event::Timestamp => self.regs.isr().read().tsf().bit_is_set(),
+            Event::Tamper => {
+                self.is_tamper_detected(TamperInput::Tamper1)
+                    || self.is_tamper_detected(TamperInput::Tamper2)
+            }
         }
     }
 
@@ -777,6 +1211,15 @@ impl Rtc {
                         .write(|w| w.pr21().clear_bit_by_one())
                 };
             }
+            Event::Tamper => {
+                self.clear_tamper_flag(TamperInput::Tamper1);
+                self.clear_tamper_flag(TamperInput::Tamper2);
+                unsafe {
+                    (*pac::EXTI::ptr())
+                        .pr()
+                        .write(|w| w.pr21().clear_bit_by_one())
+                };
+            }
         }
     }
 }
@@ -840,6 +1283,17 @@ fn decode_year(dr: &dr::R) -> u16 {
     year as u16
 }
 
+/// Sub-second counter value at `nano` nanoseconds
+const fn nano_to_ss(nano: u32, prediv_s: u16) -> u16 {
+    let ticks = (nano as u64 * (prediv_s as u64 + 1)) / 1_000_000_000;
+    prediv_s - ticks as u16
+}
+
+/// Alarm sub-second register value comparing the `bits` least significant bits with `ss`
+const fn alarm_ssr(ss: u16, bits: u8) -> u32 {
+    (bits as u32) << 24 | ss as u32
+}
+
 const fn ss_to_nano(ss: u16, prediv_s: u16) -> u32 {
     let ss = ss as u32;
     let prediv_s = prediv_s as u32;
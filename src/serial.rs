@@ -22,6 +22,8 @@ use enumflags2::BitFlags;
 use crate::pacext::uart::UartRB;
 mod hal_02;
 mod hal_1;
+#[cfg(feature = "async")]
+mod hal_async;
 
 mod uart_impls;
 use uart_impls::RBExt;
@@ -158,6 +160,16 @@ pub trait Instance: rcc::Instance + crate::Ptr<RB: RBExt> + CommonPins {
     fn peri_address() -> u32 {
         unsafe { &*Self::PTR }.peri_address()
     }
+
+    /// Waker of the task awaiting received data.
+    #[cfg(feature = "async")]
+    #[doc(hidden)]
+    fn rx_waker() -> &'static atomic_waker::AtomicWaker;
+
+    /// Waker of the task awaiting the transmitter.
+    #[cfg(feature = "async")]
+    #[doc(hidden)]
+    fn tx_waker() -> &'static atomic_waker::AtomicWaker;
 }
 
 /// Trait for [`Rx`] interrupt handling.
@@ -398,7 +410,19 @@ macro_rules! halUsart {
         pub type $Tx = Tx<$USART>;
         pub type $Rx = Rx<$USART>;
 
-        impl Instance for $USART {}
+        impl Instance for $USART {
+            #[cfg(feature = "async")]
+            fn rx_waker() -> &'static atomic_waker::AtomicWaker {
+                static WAKER: atomic_waker::AtomicWaker = atomic_waker::AtomicWaker::new();
+                &WAKER
+            }
+
+            #[cfg(feature = "async")]
+            fn tx_waker() -> &'static atomic_waker::AtomicWaker {
+                static WAKER: atomic_waker::AtomicWaker = atomic_waker::AtomicWaker::new();
+                &WAKER
+            }
+        }
     };
 }
 
@@ -416,7 +440,19 @@ macro_rules! halUart {
         pub type $Tx = Tx<$UART>;
         pub type $Rx = Rx<$UART>;
 
-        impl Instance for $UART {}
+        impl Instance for $UART {
+            #[cfg(feature = "async")]
+            fn rx_waker() -> &'static atomic_waker::AtomicWaker {
+                static WAKER: atomic_waker::AtomicWaker = atomic_waker::AtomicWaker::new();
+                &WAKER
+            }
+
+            #[cfg(feature = "async")]
+            fn tx_waker() -> &'static atomic_waker::AtomicWaker {
+                static WAKER: atomic_waker::AtomicWaker = atomic_waker::AtomicWaker::new();
+                &WAKER
+            }
+        }
     };
 }
 
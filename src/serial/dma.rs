@@ -10,6 +10,9 @@ use crate::ReadFlags;
 
 use nb;
 
+#[cfg(feature = "async")]
+mod hal_async;
+
 #[non_exhaustive]
 pub enum Error {
     SerialError(super::Error),
@@ -0,0 +1,170 @@
+//! Async serial transferring the data with DMA
+//!
+//! Besides [`Serial::on_interrupt`](crate::serial::Serial::on_interrupt) in the `USARTx`/`UARTx`
+//! interrupt handler, [`StreamX::on_interrupt`](crate::dma::StreamX::on_interrupt) must be called
+//! from the interrupt handlers of the used DMA streams. The DMA requests of the serial have to be
+//! enabled with [`Config::dma`](crate::serial::Config::dma).
+
+use core::{future::poll_fn, mem::transmute, task::Poll};
+
+use super::{
+    Channel, ChannelX, DMASet, DMATransfer, Instance, MemoryToPeripheral, PeripheralToMemory,
+    RBExt, Rx, RxDMA, SerialDma, Stream, Tx, TxDMA,
+};
+use crate::serial::{Error, Flag, RxEvent};
+use crate::OnDrop;
+
+impl<Serial_: Instance, TX_STREAM, const TX_CH: u8, RX_TRANSFER>
+    SerialDma<Serial_, TxDMA<Serial_, TX_STREAM, TX_CH>, RX_TRANSFER>
+where
+    TX_STREAM: Stream,
+    ChannelX<TX_CH>: Channel,
+    Tx<Serial_>: DMASet<TX_STREAM, TX_CH, MemoryToPeripheral>,
+{
+    /// Writes `bytes` with DMA, waits until the last byte is moved to the serial
+    async fn write_dma_async(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        // NOTE(unsafe) the guard destroys the transfer, which stops the stream,
+        // before `bytes` goes out of scope, also if this future is dropped
+        self.tx
+            .create_transfer(unsafe { transmute::<&[u8], &'static [u8]>(bytes) });
+        // Destroys the transfer when dropped, which stops the stream
+        let mut tx = OnDrop::new(&mut self.tx, |tx| {
+            if tx.created() {
+                tx.destroy_transfer()
+            }
+        });
+        let transfer = tx.tx_transfer.as_mut().unwrap();
+
+        transfer.start(|_| {});
+        let result = poll_fn(|cx| transfer.poll_complete(cx)).await;
+        drop(tx);
+
+        // A DMA transfer error is an error of the bus matrix
+        result.map_err(|_| Error::Other)
+    }
+}
+
+impl<Serial_: Instance, TX_TRANSFER, RX_STREAM, const RX_CH: u8>
+    SerialDma<Serial_, TX_TRANSFER, RxDMA<Serial_, RX_STREAM, RX_CH>>
+where
+    RX_STREAM: Stream,
+    ChannelX<RX_CH>: Channel,
+    Rx<Serial_>: DMASet<RX_STREAM, RX_CH, PeripheralToMemory>,
+{
+    /// Reads bytes into `buffer` with DMA until it is full or the line goes idle after a received byte.
+    ///
+    /// Waits for at least one byte, at most 65535 bytes are read at once.
+    /// Returns the number of bytes read.
+    pub async fn read_until_idle(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        // NDTR is limited to 16 bits
+        let len = buffer.len().min(u16::MAX as usize);
+        if len == 0 {
+            return Ok(0);
+        }
+        let buffer = &mut buffer[..len];
+
+        let Self { hal_serial, rx, .. } = self;
+        let usart = &*hal_serial.rx.usart;
+
+        loop {
+            // Clear an idle line detected before this read. With RXNE set
+            // the DMA reads the pending byte, which clears IDLE.
+            let flags = usart.flags();
+            if flags.contains(Flag::Idle) && !flags.contains(Flag::RxNotEmpty) {
+                usart.clear_idle_interrupt();
+            }
+
+            // NOTE(unsafe) the guard destroys the transfer, which stops the stream,
+            // before `buffer` goes out of scope, also if this future is dropped
+            rx.create_transfer(unsafe { transmute::<&mut [u8], &'static mut [u8]>(&mut *buffer) });
+            let mut rx = OnDrop::new(&mut *rx, |rx| {
+                if rx.created() {
+                    rx.destroy_transfer()
+                }
+            });
+            let transfer = rx.rx_transfer.as_mut().unwrap();
+            transfer.start(|_| {});
+
+            let result = poll_fn(|cx| {
+                Serial_::rx_waker().register(cx.waker());
+                let flags = usart.flags();
+                if flags.intersects(
+                    Flag::ParityError | Flag::FramingError | Flag::Noise | Flag::Overrun,
+                ) {
+                    transfer.pause(|_| {});
+                    let error = usart.check_and_clear_error_flags().err();
+                    return Poll::Ready(Err(error.unwrap_or(Error::Other)));
+                }
+                match transfer.poll_complete(cx) {
+                    Poll::Ready(Ok(())) => return Poll::Ready(Ok(len)),
+                    // A DMA transfer error is an error of the bus matrix
+                    Poll::Ready(Err(_)) => return Poll::Ready(Err(Error::Other)),
+                    Poll::Pending => {}
+                }
+                if flags.contains(Flag::Idle) {
+                    transfer.pause(|_| {});
+                    let count = len - transfer.number_of_transfers() as usize;
+                    // A byte received after the stream was stopped is left for the next read
+                    if !usart.is_rx_not_empty() {
+                        usart.clear_idle_interrupt();
+                    }
+                    return Poll::Ready(Ok(count));
+                }
+                usart.listen_rx(None, Some(RxEvent::Idle.into()));
+                usart.enable_error_interrupt_generation();
+                Poll::Pending
+            })
+            .await;
+            drop(rx);
+
+            usart.listen_rx(Some(RxEvent::Idle.into()), None);
+            usart.disable_error_interrupt_generation();
+            match result {
+                // IDLE of a byte received before this read
+                Ok(0) => {}
+                result => return result,
+            }
+        }
+    }
+}
+
+impl<Serial_: Instance, TX_TRANSFER, RX_TRANSFER> embedded_io::ErrorType
+    for SerialDma<Serial_, TX_TRANSFER, RX_TRANSFER>
+{
+    type Error = Error;
+}
+
+impl<Serial_: Instance, TX_STREAM, const TX_CH: u8, RX_TRANSFER> embedded_io_async::Write
+    for SerialDma<Serial_, TxDMA<Serial_, TX_STREAM, TX_CH>, RX_TRANSFER>
+where
+    TX_STREAM: Stream,
+    ChannelX<TX_CH>: Channel,
+    Tx<Serial_>: DMASet<TX_STREAM, TX_CH, MemoryToPeripheral>,
+{
+    /// Writes up to 65535 bytes with DMA
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        let len = buf.len().min(u16::MAX as usize);
+        if len > 0 {
+            self.write_dma_async(&buf[..len]).await?;
+        }
+        Ok(len)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        embedded_io_async::Write::flush(&mut self.hal_serial.tx).await
+    }
+}
+
+impl<Serial_: Instance, TX_TRANSFER, RX_STREAM, const RX_CH: u8> embedded_io_async::Read
+    for SerialDma<Serial_, TX_TRANSFER, RxDMA<Serial_, RX_STREAM, RX_CH>>
+where
+    RX_STREAM: Stream,
+    ChannelX<RX_CH>: Channel,
+    Rx<Serial_>: DMASet<RX_STREAM, RX_CH, PeripheralToMemory>,
+{
+    /// Reads with DMA until the buffer is full or the line goes idle
+    #[inline(always)]
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        self.read_until_idle(buf).await
+    }
+}
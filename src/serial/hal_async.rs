@@ -0,0 +1,195 @@
+//! Async serial driven by the USART interrupt
+//!
+//! [`Serial::on_interrupt`] must be called from the `USARTx`/`UARTx` interrupt handler:
+//!
+//! ```
+//! #[interrupt]
+//! fn USART1() {
+//!     Serial1::on_interrupt();
+//! }
+//! ```
+
+use super::{Error, Event, Flag, Instance, RBExt, Rx, RxEvent, Serial, Tx, TxEvent};
+use core::{future::poll_fn, task::Poll};
+use enumflags2::BitFlags;
+
+impl<USART: Instance> Serial<USART> {
+    /// Handles the `USARTx`/`UARTx` interrupt for async reads and writes.
+    ///
+    /// Disables the serial interrupts and wakes the tasks awaiting the receiver and the transmitter,
+    /// which check the status flags and listen again if they have to wait.
+    pub fn on_interrupt() {
+        let usart = unsafe { &*USART::PTR };
+        usart.listen_event(Some(BitFlags::<Event>::ALL), None);
+        usart.disable_error_interrupt_generation();
+        USART::rx_waker().wake();
+        USART::tx_waker().wake();
+    }
+}
+
+impl<USART: Instance> Rx<USART> {
+    /// Reads bytes into `buffer` until it is full or the line goes idle after a received byte.
+    ///
+    /// Waits for at least one byte, an idle line before it is ignored.
+    /// Returns the number of bytes read.
+    pub async fn read_until_idle(&mut self, buffer: &mut [u8]) -> Result<usize, Error> {
+        if buffer.is_empty() {
+            return Ok(0);
+        }
+
+        let mut count = 0;
+        // Clear an idle line detected before this read, reading SR then DR clears IDLE.
+        // A byte pending since then is kept.
+        if self.usart.flags().contains(Flag::Idle) {
+            match self.usart.read_u8() {
+                Ok(byte) => {
+                    buffer[0] = byte;
+                    count = 1;
+                }
+                Err(nb::Error::Other(e)) => return Err(e),
+                Err(nb::Error::WouldBlock) => self.usart.clear_idle_interrupt(),
+            }
+            if count == buffer.len() {
+                return Ok(count);
+            }
+        }
+
+        // IDLE only ends the read after a byte received since it was cleared
+        let mut received = false;
+        let result = poll_fn(|cx| {
+            USART::rx_waker().register(cx.waker());
+            loop {
+                let flags = self.usart.flags();
+                if flags.contains(Flag::RxNotEmpty) {
+                    // Reading the byte also clears IDLE
+                    match self.usart.read_u8() {
+                        Ok(byte) => {
+                            buffer[count] = byte;
+                            count += 1;
+                            received = true;
+                        }
+                        Err(nb::Error::Other(e)) => return Poll::Ready(Err(e)),
+                        Err(nb::Error::WouldBlock) => {}
+                    }
+                    // IDLE together with RXNE means the line went idle after this byte
+                    if count == buffer.len() || (received && flags.contains(Flag::Idle)) {
+                        return Poll::Ready(Ok(count));
+                    }
+                } else if flags.contains(Flag::Idle) {
+                    self.usart.clear_idle_interrupt();
+                    if received {
+                        return Poll::Ready(Ok(count));
+                    }
+                } else {
+                    self.usart
+                        .listen_rx(None, Some(RxEvent::RxNotEmpty | RxEvent::Idle));
+                    return Poll::Pending;
+                }
+            }
+        })
+        .await;
+
+        self.usart
+            .listen_rx(Some(RxEvent::RxNotEmpty | RxEvent::Idle), None);
+        result
+    }
+}
+
+impl<USART: Instance> embedded_io_async::Read for Rx<USART> {
+    /// Waits for at least one byte and reads all the bytes already received
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let result = poll_fn(|cx| {
+            USART::rx_waker().register(cx.waker());
+            let mut count = 0;
+            while count < buf.len() {
+                match self.usart.read_u8() {
+                    Ok(byte) => {
+                        buf[count] = byte;
+                        count += 1;
+                    }
+                    Err(nb::Error::Other(e)) => return Poll::Ready(Err(e)),
+                    Err(nb::Error::WouldBlock) => break,
+                }
+            }
+            if count > 0 {
+                Poll::Ready(Ok(count))
+            } else {
+                self.usart.listen_rx(None, Some(RxEvent::RxNotEmpty.into()));
+                Poll::Pending
+            }
+        })
+        .await;
+
+        self.usart.listen_rx(Some(RxEvent::RxNotEmpty.into()), None);
+        result
+    }
+}
+
+impl<USART: Instance> embedded_io_async::Write for Tx<USART> {
+    /// Waits until the transmit register is empty and writes as many bytes as it accepts
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        let count = poll_fn(|cx| {
+            USART::tx_waker().register(cx.waker());
+            let count = buf
+                .iter()
+                .take_while(|&&byte| self.usart.write_u8(byte).is_ok())
+                .count();
+            if count > 0 {
+                Poll::Ready(count)
+            } else {
+                self.usart.listen_tx(None, Some(TxEvent::TxEmpty.into()));
+                Poll::Pending
+            }
+        })
+        .await;
+
+        self.usart.listen_tx(Some(TxEvent::TxEmpty.into()), None);
+        Ok(count)
+    }
+
+    /// Waits until the transmission of the last byte is complete
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        poll_fn(|cx| {
+            USART::tx_waker().register(cx.waker());
+            if self.usart.flush().is_ok() {
+                Poll::Ready(())
+            } else {
+                self.usart
+                    .listen_tx(None, Some(TxEvent::TransmissionComplete.into()));
+                Poll::Pending
+            }
+        })
+        .await;
+
+        self.usart
+            .listen_tx(Some(TxEvent::TransmissionComplete.into()), None);
+        Ok(())
+    }
+}
+
+impl<USART: Instance> embedded_io_async::Read for Serial<USART> {
+    #[inline(always)]
+    async fn read(&mut self, buf: &mut [u8]) -> Result<usize, Self::Error> {
+        embedded_io_async::Read::read(&mut self.rx, buf).await
+    }
+}
+
+impl<USART: Instance> embedded_io_async::Write for Serial<USART> {
+    #[inline(always)]
+    async fn write(&mut self, buf: &[u8]) -> Result<usize, Self::Error> {
+        embedded_io_async::Write::write(&mut self.tx, buf).await
+    }
+
+    #[inline(always)]
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        embedded_io_async::Write::flush(&mut self.tx).await
+    }
+}
@@ -36,6 +36,10 @@ pub struct Mode {
 
 mod hal_02;
 mod hal_1;
+#[cfg(feature = "async")]
+mod hal_async;
+#[cfg(feature = "async")]
+pub use hal_async::SpiDma;
 
 use crate::pac::{spi1, RCC};
 use crate::rcc::{self, Rcc};
@@ -54,6 +58,8 @@ pub enum Error {
     ModeFault,
     /// CRC error
     Crc,
+    /// DMA transfer error, e.g. a buffer in memory the DMA can't access
+    Dma,
 }
 
 /// SPI interrupt events
@@ -34,7 +34,7 @@ impl Error for super::Error {
         match self {
             Self::Overrun => ErrorKind::Overrun,
             Self::ModeFault => ErrorKind::ModeFault,
-            Self::Crc => ErrorKind::Other,
+            Self::Crc | Self::Dma => ErrorKind::Other,
         }
     }
 }
@@ -0,0 +1,312 @@
+//! Async SPI bus transferring the data with a pair of DMA streams
+//!
+//! [`StreamX::on_interrupt`](crate::dma::StreamX::on_interrupt) must be called
+//! from the interrupt handlers of both streams:
+//!
+//! ```
+//! let streams = StreamsTuple::new(dp.DMA2, &mut rcc);
+//! let mut spi = dp.SPI1.spi(pins, mode, 1.MHz(), &mut rcc)
+//!     .use_dma_async(streams.3, streams.2);
+//! spi.transfer_in_place(&mut buffer).await?;
+//!
+//! #[interrupt]
+//! fn DMA2_STREAM2() {
+//!     StreamX::<DMA2, 2>::on_interrupt();
+//! }
+//!
+//! #[interrupt]
+//! fn DMA2_STREAM3() {
+//!     StreamX::<DMA2, 3>::on_interrupt();
+//! }
+//! ```
+
+use core::{
+    future::poll_fn,
+    mem,
+    ptr::{addr_of, addr_of_mut},
+    sync::atomic::{compiler_fence, Ordering},
+    task::Poll,
+};
+
+use super::{Error, FrameSize, Instance, Spi};
+use crate::dma::{
+    config::Priority,
+    poll_stream, stream_disable,
+    traits::{Channel, DMASet, Stream},
+    ChannelX, DmaChannel, DmaDataSize, DmaDirection, DmaFlowController, MemoryToPeripheral,
+    PeripheralToMemory,
+};
+use crate::OnDrop;
+
+/// SPI bus using DMA streams for async transfers
+///
+/// Created by [`Spi::use_dma_async`], implements [`embedded_hal_async::spi::SpiBus`].
+pub struct SpiDma<SPI: Instance, TX_STREAM, const TX_CH: u8, RX_STREAM, const RX_CH: u8, W = u8> {
+    spi: Spi<SPI, false, W>,
+    tx_stream: TX_STREAM,
+    rx_stream: RX_STREAM,
+}
+
+impl<SPI: Instance, W: FrameSize> Spi<SPI, false, W> {
+    /// Converts the SPI into an async bus transmitting with `tx_stream` and receiving with `rx_stream`
+    pub fn use_dma_async<TX_STREAM, const TX_CH: u8, RX_STREAM, const RX_CH: u8>(
+        self,
+        tx_stream: TX_STREAM,
+        rx_stream: RX_STREAM,
+    ) -> SpiDma<SPI, TX_STREAM, TX_CH, RX_STREAM, RX_CH, W>
+    where
+        TX_STREAM: Stream,
+        ChannelX<TX_CH>: Channel,
+        SPI: DMASet<TX_STREAM, TX_CH, MemoryToPeripheral>,
+
+        RX_STREAM: Stream,
+        ChannelX<RX_CH>: Channel,
+        SPI: DMASet<RX_STREAM, RX_CH, PeripheralToMemory>,
+    {
+        self.spi.cr2().modify(|_, w| {
+            w.txdmaen().enabled();
+            w.rxdmaen().enabled()
+        });
+        SpiDma {
+            spi: self,
+            tx_stream,
+            rx_stream,
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn configure_stream<STREAM: Stream>(
+    stream: &mut STREAM,
+    channel: DmaChannel,
+    direction: DmaDirection,
+    size: DmaDataSize,
+    peripheral: u32,
+    memory: u32,
+    memory_increment: bool,
+    len: u16,
+) {
+    stream_disable(stream);
+    stream.clear_all_flags();
+    stream.set_channel(channel);
+    stream.set_priority(Priority::Medium);
+    stream.set_direction(direction);
+    stream.set_flow_controller(DmaFlowController::Dma);
+    stream.set_circular_mode(false);
+    stream.set_double_buffer(false);
+    // Direct mode, every SPI request moves one frame
+    stream.set_fifo_enable(false);
+    unsafe {
+        stream.set_memory_size(size);
+        stream.set_peripheral_size(size);
+    }
+    stream.set_peripheral_increment(false);
+    stream.set_memory_increment(memory_increment);
+    stream.set_peripheral_address(peripheral);
+    stream.set_memory_address(memory);
+    stream.set_number_of_transfers(len);
+}
+
+impl<SPI, TX_STREAM, const TX_CH: u8, RX_STREAM, const RX_CH: u8, W>
+    SpiDma<SPI, TX_STREAM, TX_CH, RX_STREAM, RX_CH, W>
+where
+    SPI: Instance,
+    W: FrameSize,
+    TX_STREAM: Stream,
+    ChannelX<TX_CH>: Channel,
+    RX_STREAM: Stream,
+    ChannelX<RX_CH>: Channel,
+{
+    /// Disables the DMA requests and returns the SPI and the streams
+    pub fn release(self) -> (Spi<SPI, false, W>, TX_STREAM, RX_STREAM) {
+        self.spi.spi.cr2().modify(|_, w| {
+            w.txdmaen().disabled();
+            w.rxdmaen().disabled()
+        });
+        (self.spi, self.tx_stream, self.rx_stream)
+    }
+
+    /// Transmits `len` frames from `tx` and receives them to `rx`.
+    /// Without increment the same frame is sent or overwritten for every transfer.
+    async fn transfer_dma(
+        &mut self,
+        mut tx: u32,
+        tx_increment: bool,
+        mut rx: u32,
+        rx_increment: bool,
+        mut len: usize,
+    ) -> Result<(), Error> {
+        let size = if W::DFF {
+            DmaDataSize::HalfWord
+        } else {
+            DmaDataSize::Byte
+        };
+        let dr = self.spi.spi.dr().as_ptr() as u32;
+
+        while len > 0 {
+            // NDTR is limited to 16 bits
+            let chunk = len.min(u16::MAX as usize);
+
+            // Discard stale received data, which also clears the overrun flag
+            let spi = &self.spi.spi;
+            if spi.sr().read().rxne().bit_is_set() {
+                let _ = spi.dr().read();
+            }
+            let _ = spi.sr().read();
+
+            configure_stream(
+                &mut self.rx_stream,
+                ChannelX::<RX_CH>::VALUE,
+                DmaDirection::PeripheralToMemory,
+                size,
+                dr,
+                rx,
+                rx_increment,
+                chunk as u16,
+            );
+            configure_stream(
+                &mut self.tx_stream,
+                ChannelX::<TX_CH>::VALUE,
+                DmaDirection::MemoryToPeripheral,
+                size,
+                dr,
+                tx,
+                tx_increment,
+                chunk as u16,
+            );
+
+            // Disables both streams, i.e. aborts the transfer if the future is dropped
+            let mut streams =
+                OnDrop::new((&mut self.tx_stream, &mut self.rx_stream), |(tx, rx)| {
+                    stream_disable(*tx);
+                    stream_disable(*rx);
+                    compiler_fence(Ordering::SeqCst);
+                });
+            // "Preceding reads and writes cannot be moved past subsequent writes"
+            compiler_fence(Ordering::Release);
+            unsafe {
+                streams.1.enable();
+                streams.0.enable();
+            }
+
+            let mut tx_done = false;
+            let mut rx_done = false;
+            let result = poll_fn(|cx| {
+                if !tx_done {
+                    match poll_stream(&mut *streams.0, cx) {
+                        Poll::Ready(Ok(())) => tx_done = true,
+                        Poll::Ready(Err(_)) => return Poll::Ready(Err(Error::Dma)),
+                        Poll::Pending => {}
+                    }
+                }
+                if !rx_done {
+                    match poll_stream(&mut *streams.1, cx) {
+                        Poll::Ready(Ok(())) => rx_done = true,
+                        Poll::Ready(Err(_)) => return Poll::Ready(Err(Error::Dma)),
+                        Poll::Pending => {}
+                    }
+                }
+                if tx_done && rx_done {
+                    Poll::Ready(Ok(()))
+                } else {
+                    Poll::Pending
+                }
+            })
+            .await;
+            drop(streams);
+            compiler_fence(Ordering::Acquire);
+            result?;
+
+            let sr = self.spi.spi.sr().read();
+            if sr.modf().bit_is_set() {
+                // Write to CR1 to clear MODF
+                self.spi.spi.cr1().modify(|_r, w| w);
+                return Err(Error::ModeFault);
+            }
+
+            let step = (chunk * mem::size_of::<W>()) as u32;
+            if tx_increment {
+                tx += step;
+            }
+            if rx_increment {
+                rx += step;
+            }
+            len -= chunk;
+        }
+
+        Ok(())
+    }
+}
+
+impl<SPI, TX_STREAM, const TX_CH: u8, RX_STREAM, const RX_CH: u8, W> embedded_hal::spi::ErrorType
+    for SpiDma<SPI, TX_STREAM, TX_CH, RX_STREAM, RX_CH, W>
+where
+    SPI: Instance,
+{
+    type Error = Error;
+}
+
+impl<SPI, TX_STREAM, const TX_CH: u8, RX_STREAM, const RX_CH: u8, W>
+    embedded_hal_async::spi::SpiBus<W> for SpiDma<SPI, TX_STREAM, TX_CH, RX_STREAM, RX_CH, W>
+where
+    SPI: Instance,
+    W: FrameSize + 'static,
+    TX_STREAM: Stream,
+    ChannelX<TX_CH>: Channel,
+    RX_STREAM: Stream,
+    ChannelX<RX_CH>: Channel,
+{
+    async fn read(&mut self, words: &mut [W]) -> Result<(), Self::Error> {
+        let dummy = W::default();
+        let len = words.len();
+        self.transfer_dma(
+            addr_of!(dummy) as u32,
+            false,
+            words.as_mut_ptr() as u32,
+            true,
+            len,
+        )
+        .await
+    }
+
+    async fn write(&mut self, words: &[W]) -> Result<(), Self::Error> {
+        let mut dummy = W::default();
+        self.transfer_dma(
+            words.as_ptr() as u32,
+            true,
+            addr_of_mut!(dummy) as u32,
+            false,
+            words.len(),
+        )
+        .await
+    }
+
+    async fn transfer(&mut self, read: &mut [W], write: &[W]) -> Result<(), Self::Error> {
+        let common = read.len().min(write.len());
+        self.transfer_dma(
+            write.as_ptr() as u32,
+            true,
+            read.as_mut_ptr() as u32,
+            true,
+            common,
+        )
+        .await?;
+
+        if read.len() > common {
+            self.read(&mut read[common..]).await
+        } else {
+            self.write(&write[common..]).await
+        }
+    }
+
+    async fn transfer_in_place(&mut self, words: &mut [W]) -> Result<(), Self::Error> {
+        // Every frame is sent before the received frame overwrites it
+        let ptr = words.as_mut_ptr() as u32;
+        self.transfer_dma(ptr, true, ptr, true, words.len()).await
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        while self.spi.is_busy() {}
+        Ok(())
+    }
+}
@@ -29,6 +29,34 @@ impl Deref for SysCfg {
     }
 }
 
+#[cfg(any(feature = "gpio-f427", feature = "gpio-f469"))]
+impl SysCfg {
+    /// Maps the second flash bank at `0x0800_0000` and the first bank after it.
+    ///
+    /// Execution continues at the same address in the other bank, so both banks usually
+    /// start with the same bootloader selecting the firmware. The mapping is lost on reset.
+    pub fn set_flash_bank_swap(&mut self, swap: bool) {
+        self.0.memrmp().modify(|r, w| unsafe {
+            w.bits(if swap {
+                r.bits() | FB_MODE
+            } else {
+                r.bits() & !FB_MODE
+            })
+        });
+        cortex_m::asm::dsb();
+        cortex_m::asm::isb();
+    }
+
+    /// Returns true if the flash banks are swapped
+    pub fn is_flash_bank_swapped(&self) -> bool {
+        self.0.memrmp().read().bits() & FB_MODE != 0
+    }
+}
+
+/// Flash bank mode bit of MEMRMP
+#[cfg(any(feature = "gpio-f427", feature = "gpio-f469"))]
+const FB_MODE: u32 = 1 << 8;
+
 #[cfg(feature = "defmt")]
 impl defmt::Format for SysCfg {
     fn format(&self, f: defmt::Formatter) {
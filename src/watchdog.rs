@@ -1,8 +1,11 @@
 //! Watchdog peripherals
 
-use crate::pac::{DBGMCU, IWDG};
+use crate::pac::{DBGMCU, IWDG, WWDG};
+use crate::rcc::{Enable, Rcc, Reset};
 use core::fmt;
 use embedded_hal_02::watchdog::{Watchdog, WatchdogEnable};
+use fugit::HertzU32 as Hertz;
+use fugit::MicrosDurationU32 as MicroSeconds;
 use fugit::MillisDurationU32 as MilliSeconds;
 
 /// Wraps the Independent Watchdog (IWDG) peripheral
@@ -131,3 +134,158 @@ impl Watchdog for IndependentWatchdog {
         self.feed()
     }
 }
+
+/// Wraps the Window Watchdog (WWDG) peripheral
+///
+/// The counter is clocked by PCLK1 / 4096 / 2^WDGTB, which gives timeouts
+/// up to about 50 ms at 42 MHz.
+pub struct WindowWatchdog {
+    wwdg: WWDG,
+    pclk1: Hertz,
+    /// Counter value written on feed
+    reload: u8,
+    /// Largest counter value the watchdog can be fed at
+    window: u8,
+}
+
+#[cfg(feature = "defmt")]
+impl defmt::Format for WindowWatchdog {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "WindowWatchdog");
+    }
+}
+
+impl fmt::Debug for WindowWatchdog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("WindowWatchdog")
+    }
+}
+
+/// Window watchdog error
+#[cfg_attr(feature = "defmt", derive(defmt::Format))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    /// Fed before the window opened, the watchdog was not reloaded
+    OutsideWindow,
+}
+
+/// WWDG prescaler before WDGTB
+const WWDG_DIV: u64 = 4096;
+/// The device resets when the counter decrements from 0x40 to 0x3F
+const WWDG_MIN: u8 = 0x3F;
+const WWDG_MAX: u8 = 0x7F;
+
+impl WindowWatchdog {
+    /// Creates a new `WindowWatchdog` without starting it. Call `start` or `start_windowed`
+    /// to start the watchdog, it can't be stopped afterwards.
+    pub fn new(wwdg: WWDG, rcc: &mut Rcc) -> Self {
+        WWDG::enable(rcc);
+        WWDG::reset(rcc);
+
+        Self {
+            wwdg,
+            pclk1: rcc.clocks.pclk1(),
+            reload: WWDG_MAX,
+            window: WWDG_MAX,
+        }
+    }
+
+    /// Debug window watchdog stopped when core is halted
+    pub fn stop_on_debug(&self, dbgmcu: &DBGMCU, stop: bool) {
+        dbgmcu.apb1_fz().modify(|_, w| w.dbg_wwdg_stop().bit(stop));
+    }
+
+    /// Counter ticks of `time` with the prescaler `wdgtb`
+    fn ticks(&self, time: MicroSeconds, wdgtb: u8) -> u64 {
+        u64::from(time.ticks()) * u64::from(self.pclk1.raw()) / (WWDG_DIV << wdgtb) / 1_000_000
+    }
+
+    /// Starts the watchdog, it must be fed within `timeout`
+    pub fn start(&mut self, timeout: MicroSeconds) {
+        self.start_windowed(MicroSeconds::from_ticks(0), timeout);
+    }
+
+    /// Starts the watchdog, it must be fed after `window` and within `timeout` since the
+    /// last feed.
+    ///
+    /// # Panics
+    ///
+    /// * When the timeout exceeds 64 counter ticks with the largest prescaler.
+    /// * When the window is not shorter than the timeout.
+    pub fn start_windowed(&mut self, window: MicroSeconds, timeout: MicroSeconds) {
+        let wdgtb = (0..4)
+            .find(|&wdgtb| self.ticks(timeout, wdgtb) <= u64::from(WWDG_MAX - WWDG_MIN))
+            .expect("Watchdog timeout too high");
+        let timeout_ticks = self.ticks(timeout, wdgtb).max(1) as u8;
+        let window_ticks = self.ticks(window, wdgtb) as u8;
+        assert!(window_ticks < timeout_ticks, "Watchdog window too long");
+
+        self.reload = WWDG_MIN + timeout_ticks;
+        self.window = self.reload - window_ticks;
+        self.wwdg
+            .cfr()
+            .modify(|_, w| w.wdgtb().set(wdgtb).w().set(self.window));
+        self.wwdg
+            .cr()
+            .write(|w| w.wdga().set_bit().t().set(self.reload));
+    }
+
+    /// Returns the timeout
+    pub fn interval(&self) -> MicroSeconds {
+        let wdgtb = self.wwdg.cfr().read().wdgtb().bits();
+        let ticks = u64::from(self.reload - WWDG_MIN);
+        MicroSeconds::from_ticks(
+            (ticks * (WWDG_DIV << wdgtb) * 1_000_000 / u64::from(self.pclk1.raw())) as u32,
+        )
+    }
+
+    /// Returns true if the watchdog can be fed
+    pub fn is_window_open(&self) -> bool {
+        self.wwdg.cr().read().t().bits() <= self.window
+    }
+
+    /// Reloads the counter, feeding it before the window opens would reset the device,
+    /// so the counter is not reloaded and an error is returned instead.
+    pub fn feed(&mut self) -> Result<(), Error> {
+        if !self.is_window_open() {
+            return Err(Error::OutsideWindow);
+        }
+        self.wwdg
+            .cr()
+            .write(|w| w.wdga().set_bit().t().set(self.reload));
+        Ok(())
+    }
+
+    /// Enables the early wakeup interrupt, generated one counter tick before the reset.
+    ///
+    /// The `WWDG` handler can log the failure or feed the watchdog.
+    /// The interrupt can only be disabled by a reset.
+    pub fn listen_early_wakeup(&mut self) {
+        self.wwdg.cfr().modify(|_, w| w.ewi().set_bit());
+    }
+
+    /// Returns true if the early wakeup interrupt is pending
+    pub fn is_early_wakeup(&self) -> bool {
+        self.wwdg.sr().read().ewif().bit_is_set()
+    }
+
+    /// Clears the early wakeup interrupt flag
+    pub fn clear_early_wakeup(&mut self) {
+        self.wwdg.sr().write(|w| w.ewif().clear_bit());
+    }
+}
+
+impl WatchdogEnable for WindowWatchdog {
+    type Time = MicroSeconds;
+
+    fn start<T: Into<Self::Time>>(&mut self, period: T) {
+        self.start(period.into())
+    }
+}
+
+impl Watchdog for WindowWatchdog {
+    /// Waits for the window to open before feeding
+    fn feed(&mut self) {
+        while self.feed().is_err() {}
+    }
+}